@@ -0,0 +1,66 @@
+use crate::filters::match_glob;
+use crate::git::{find_git_root, recent_changes};
+use crate::options::FlattenOptions;
+use std::path::Path;
+use tracing::warn;
+
+/// How many of the latest commits count as "recently changed" when prioritising files.
+const RECENT_COMMITS: usize = 50;
+
+/// A file competing for a place in the token budget.
+pub(crate) struct BudgetItem<'a> {
+    pub path: &'a Path,
+    pub relative_path: &'a Path,
+    pub size: u64,
+    /// Tokens the file adds to the output, markers included.
+    pub cost: usize,
+}
+
+/// Orders items by priority for the token budget: files matching an earlier include glob
+/// first (in the order the globs are listed), then files changed recently in git
+/// (uncommitted first, then by commit recency), then smaller files.
+pub(crate) fn budget_order(items: &[BudgetItem], options: &FlattenOptions) -> Vec<usize> {
+    let recency = items
+        .first()
+        .and_then(|item| find_git_root(item.path).ok().flatten())
+        .and_then(|root| match recent_changes(&root, RECENT_COMMITS) {
+            Ok(ranks) => Some(ranks),
+            Err(e) => {
+                warn!("Failed to read git history for token budget priorities: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default();
+    let include_globs = options.include_globs.as_deref().unwrap_or_default();
+
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by_key(|&i| {
+        let item = &items[i];
+        let glob_rank = include_globs
+            .iter()
+            .position(|g| match_glob(g, item.relative_path))
+            .unwrap_or(usize::MAX);
+        (
+            glob_rank,
+            recency.get(item.path).copied().unwrap_or(usize::MAX),
+            item.size,
+            item.path,
+        )
+    });
+    order
+}
+
+/// Decides which items fit into `budget` tokens, taking them in `order` (see
+/// [`budget_order`]); returns one flag per item. Each item is kept if it still fits, so a
+/// large file does not block smaller files further down the list.
+pub(crate) fn fit_token_budget(items: &[BudgetItem], order: &[usize], budget: usize) -> Vec<bool> {
+    let mut fits = vec![false; items.len()];
+    let mut remaining = budget;
+    for &i in order {
+        if items[i].cost <= remaining {
+            remaining -= items[i].cost;
+            fits[i] = true;
+        }
+    }
+    fits
+}
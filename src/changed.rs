@@ -0,0 +1,101 @@
+use crate::git::{changed_files, find_git_root, merge_base};
+use crate::options::FlattenOptions;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Directories test files are recognized in by name alone.
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs"];
+
+/// Affixes of test file stems: `foo_test.go`, `foo.test.ts`, `FooTest.java`, `test_foo.py`.
+const TEST_SUFFIXES: &[&str] = &[".test", ".spec", "_test", "_tests", "_spec", "-test", "-spec", "Test", "Tests", "Spec"];
+const TEST_PREFIXES: &[&str] = &["test_"];
+
+/// Stems too common to tie a test to a changed file.
+const GENERIC_STEMS: &[&str] = &["mod", "lib", "main", "index", "__init__", "init", "utils", "types"];
+
+/// Files added or modified since a git ref (`changed_since`) or in the working tree
+/// (`changed_only`), which restrict the walk to themselves and, with `changed_neighbors`,
+/// to the files in their directories and their tests.
+pub(crate) struct ChangedFiles {
+    root: PathBuf,
+    /// "since main" or "in the working tree", for explanations.
+    label: String,
+    files: HashSet<PathBuf>,
+    neighbors: bool,
+    dirs: HashSet<PathBuf>,
+    stems: HashSet<String>,
+}
+
+impl ChangedFiles {
+    /// Lists the changed files, if `options` asks for it. Untracked files count as added.
+    pub(crate) fn load(options: &FlattenOptions) -> Result<Option<Self>> {
+        if options.changed_since.is_none() && !options.changed_only {
+            return Ok(None);
+        }
+        let start = options.target_dirs.first().cloned().unwrap_or_else(|| PathBuf::from("."));
+        let root = find_git_root(&start)?
+            .with_context(|| format!("Cannot list changed files: {} is not in a git repository", start.display()))?;
+        let (base, label) = match &options.changed_since {
+            Some(reference) => {
+                let base = merge_base(&root, reference)
+                    .with_context(|| format!("Cannot resolve --changed-since ref '{}'", reference))?;
+                (base, format!("since {}", reference))
+            }
+            None => ("HEAD".to_string(), "in the working tree".to_string()),
+        };
+        let files = changed_files(&root, &base)?;
+        let dirs = files.iter().filter_map(|file| file.parent()).map(Path::to_path_buf).collect();
+        let stems = files
+            .iter()
+            .filter_map(|file| file.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .filter(|stem| !GENERIC_STEMS.contains(&stem.as_str()))
+            .collect();
+        Ok(Some(Self { root, label, files, neighbors: options.changed_neighbors, dirs, stems }))
+    }
+
+    /// Why `path` stays in the walk, or `None` if it is not changed nor a neighbor of a
+    /// changed file.
+    pub(crate) fn describe(&self, path: &Path) -> Option<String> {
+        if self.files.contains(path) {
+            return Some(format!("changed {}", self.label));
+        }
+        if !self.neighbors {
+            return None;
+        }
+        if path.parent().is_some_and(|dir| self.dirs.contains(dir)) {
+            return Some(format!("next to a file changed {}", self.label));
+        }
+        let relative_path = path.strip_prefix(&self.root).unwrap_or(path);
+        match test_subject(relative_path) {
+            Some(subject) if self.stems.contains(&subject) => Some(format!("test of a file changed {}", self.label)),
+            _ => None,
+        }
+    }
+
+    /// "since main" or "in the working tree".
+    pub(crate) fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// The stem of the file a test file tests: `foo` for `foo_test.go`, `foo.spec.ts`,
+/// `test_foo.py` or `tests/foo.rs`. `None` for files that do not look like tests.
+fn test_subject(relative_path: &Path) -> Option<String> {
+    let stem = relative_path.file_stem()?.to_string_lossy();
+    let affixed = TEST_SUFFIXES
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))
+        .or_else(|| TEST_PREFIXES.iter().find_map(|prefix| stem.strip_prefix(prefix)))
+        .filter(|subject| !subject.is_empty());
+    if let Some(subject) = affixed {
+        return Some(subject.to_string());
+    }
+    let in_test_dir = relative_path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|component| TEST_DIRS.contains(&component.as_os_str().to_string_lossy().as_ref()));
+    in_test_dir.then(|| stem.into_owned())
+}
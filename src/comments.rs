@@ -0,0 +1,627 @@
+use crate::language::language_for_path;
+use std::ops::Range;
+use std::path::Path;
+
+/// Lexical rules of one language family, as far as needed to tell comments from code
+/// and string literals.
+struct Syntax {
+    /// Line comment markers.
+    line: &'static [&'static str],
+    /// Block comment delimiters.
+    block: Option<(&'static str, &'static str)>,
+    /// Block comments nest (Rust).
+    nested_blocks: bool,
+    /// Comment openers that start documentation (`///`, `/**`, ...), kept unless
+    /// docstrings are stripped too.
+    doc: &'static [&'static str],
+    /// String delimiters with backslash escapes.
+    quotes: &'static [u8],
+    /// String delimiters without escapes (shell `'`, Go backticks).
+    raw_quotes: &'static [u8],
+    /// Strings end at a newline (C, Java, JS) instead of spanning lines.
+    single_line_strings: bool,
+    /// `'` starts a character literal or, in Rust, a lifetime.
+    char_literals: bool,
+    /// Python: `"""` / `'''` strings and docstrings.
+    triple_quotes: bool,
+    /// JavaScript: `/.../` regular expression literals.
+    regex_literals: bool,
+    /// Shell/YAML: `#` only starts a comment at the start of a word.
+    hash_at_word_start: bool,
+    /// Here-document operator (`<<` in shell, `<<<` in PHP).
+    heredoc: Option<&'static str>,
+    /// Rust `r#"..."#` raw strings.
+    rust_raw_strings: bool,
+    /// C++ `R"delim(...)delim"` raw strings.
+    cpp_raw_strings: bool,
+    /// SCSS/Less: `//` right after `:` is part of a URL, not a comment.
+    url_guard: bool,
+    /// PHP: `#[` starts an attribute, not a comment.
+    hash_attributes: bool,
+    /// Keep a `#!` first line.
+    shebang: bool,
+}
+
+const PLAIN: Syntax = Syntax {
+    line: &[],
+    block: None,
+    nested_blocks: false,
+    doc: &[],
+    quotes: &[],
+    raw_quotes: &[],
+    single_line_strings: false,
+    char_literals: false,
+    triple_quotes: false,
+    regex_literals: false,
+    hash_at_word_start: false,
+    heredoc: None,
+    rust_raw_strings: false,
+    cpp_raw_strings: false,
+    url_guard: false,
+    hash_attributes: false,
+    shebang: false,
+};
+
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    doc: &["///", "//!", "/**", "/*!"],
+    quotes: b"\"",
+    single_line_strings: true,
+    char_literals: true,
+    ..PLAIN
+};
+
+const RUST: Syntax = Syntax {
+    nested_blocks: true,
+    single_line_strings: false,
+    rust_raw_strings: true,
+    ..C_LIKE
+};
+
+const CPP: Syntax = Syntax { cpp_raw_strings: true, ..C_LIKE };
+
+const GO: Syntax = Syntax { raw_quotes: b"`", ..C_LIKE };
+
+const JS: Syntax = Syntax {
+    doc: &["/**"],
+    quotes: b"\"'`",
+    char_literals: false,
+    regex_literals: true,
+    ..C_LIKE
+};
+
+const PHP: Syntax = Syntax {
+    line: &["//", "#"],
+    doc: &["/**"],
+    quotes: b"\"'`",
+    single_line_strings: false,
+    char_literals: false,
+    heredoc: Some("<<<"),
+    hash_attributes: true,
+    shebang: true,
+    ..C_LIKE
+};
+
+const PYTHON: Syntax = Syntax {
+    line: &["#"],
+    quotes: b"\"'",
+    single_line_strings: true,
+    triple_quotes: true,
+    shebang: true,
+    ..PLAIN
+};
+
+const SHELL: Syntax = Syntax {
+    line: &["#"],
+    quotes: b"\"",
+    raw_quotes: b"'",
+    hash_at_word_start: true,
+    heredoc: Some("<<"),
+    shebang: true,
+    ..PLAIN
+};
+
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    quotes: b"\"'",
+    single_line_strings: true,
+    shebang: true,
+    ..PLAIN
+};
+
+const YAML: Syntax = Syntax { hash_at_word_start: true, ..HASH };
+
+const CSS: Syntax = Syntax {
+    block: Some(("/*", "*/")),
+    quotes: b"\"'",
+    single_line_strings: true,
+    ..PLAIN
+};
+
+const SCSS: Syntax = Syntax { line: &["//"], url_guard: true, ..CSS };
+
+const SQL: Syntax = Syntax {
+    line: &["--"],
+    block: Some(("/*", "*/")),
+    quotes: b"'\"",
+    ..PLAIN
+};
+
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    Some(match language_for_path(path)? {
+        "rust" => &RUST,
+        "c" | "java" | "kotlin" | "csharp" | "swift" | "protobuf" | "prisma" => &C_LIKE,
+        "cpp" => &CPP,
+        "go" => &GO,
+        "javascript" | "jsx" | "typescript" | "tsx" => &JS,
+        "php" => &PHP,
+        "python" => &PYTHON,
+        "shell" | "dockerfile" | "make" | "cmake" => &SHELL,
+        "ruby" | "toml" | "graphql" => &HASH,
+        "yaml" | "dotenv" => &YAML,
+        "css" => &CSS,
+        "scss" | "less" => &SCSS,
+        "sql" => &SQL,
+        _ => return None,
+    })
+}
+
+/// Removes comments from `content`, keeping documentation comments (and Python
+/// docstrings) unless `docstrings` is set. String literals are never touched. Lines
+/// left empty by a removed comment are dropped, along with the whitespace before a
+/// trailing comment. Content in a language the lexer does not know is returned as is.
+pub(crate) fn strip_comments(path: &Path, content: &str, docstrings: bool) -> String {
+    match syntax_for(path) {
+        Some(syntax) => Stripper::new(content, syntax, docstrings).run(),
+        None => content.to_string(),
+    }
+}
+
+/// Byte ranges of `content` taken by comments, string literals (and regexes, raw strings,
+/// heredoc bodies), in order, or `None` for a language the lexer does not know.
+/// Everything outside them is code.
+pub(crate) fn literal_spans(path: &Path, content: &str) -> Option<Vec<Range<usize>>> {
+    let mut stripper = Stripper::new(content, syntax_for(path)?, false);
+    stripper.keep_comments = true;
+    Some(stripper.scan())
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+struct Stripper<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    syntax: &'static Syntax,
+    docstrings: bool,
+    /// Copy every comment instead of removing it, so the output is the input.
+    keep_comments: bool,
+    out: String,
+    /// Where the current line starts in `out`.
+    line_start: usize,
+    /// A comment was removed from the current line.
+    line_stripped: bool,
+    /// Last non-whitespace byte of code written (for regex and docstring detection).
+    last_significant: Option<u8>,
+    /// Here-document terminators waiting for the end of the current line.
+    heredocs: Vec<String>,
+    /// Ranges of `out` holding comments and literals.
+    literals: Vec<Range<usize>>,
+}
+
+impl<'a> Stripper<'a> {
+    fn new(src: &'a str, syntax: &'static Syntax, docstrings: bool) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+            syntax,
+            docstrings,
+            keep_comments: false,
+            out: String::with_capacity(src.len()),
+            line_start: 0,
+            line_stripped: false,
+            last_significant: None,
+            heredocs: Vec::new(),
+            literals: Vec::new(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    /// Copies `src[self.pos..end]` to the output as code.
+    fn emit_to(&mut self, end: usize) {
+        let text = &self.src[self.pos..end];
+        if let Some(byte) = text.bytes().rev().find(|b| !b.is_ascii_whitespace()) {
+            self.last_significant = Some(byte);
+        }
+        self.out.push_str(text);
+        self.pos = end;
+    }
+
+    /// Copies a string or regex literal ending at `end` to the output.
+    fn emit_literal(&mut self, end: usize) {
+        let start = self.out.len();
+        self.emit_to(end);
+        self.literals.push(start..self.out.len());
+    }
+
+    /// Copies a kept comment ending at `end` to the output.
+    fn keep_comment(&mut self, end: usize) {
+        let start = self.out.len();
+        self.out.push_str(&self.src[self.pos..end]);
+        self.pos = end;
+        self.literals.push(start..self.out.len());
+    }
+
+    /// Ends the current output line at the newline at `self.pos`, dropping it if a removed
+    /// comment left it blank.
+    fn newline(&mut self) {
+        let crlf = self.pos > 0 && self.bytes[self.pos - 1] == b'\r';
+        if self.line_stripped {
+            let line = &self.out[self.line_start..];
+            let kept = line.trim_end().len();
+            if line.trim().is_empty() {
+                self.out.truncate(self.line_start);
+            } else {
+                self.out.truncate(self.line_start + kept);
+                if crlf {
+                    self.out.push('\r');
+                }
+                self.out.push('\n');
+            }
+        } else {
+            self.out.push('\n');
+        }
+        self.pos += 1;
+        self.line_start = self.out.len();
+        self.line_stripped = false;
+        self.copy_heredocs();
+    }
+
+    fn run(mut self) -> String {
+        self.strip();
+        self.out
+    }
+
+    fn scan(mut self) -> Vec<Range<usize>> {
+        self.strip();
+        debug_assert_eq!(self.out, self.src);
+        self.literals
+    }
+
+    fn strip(&mut self) {
+        if self.syntax.shebang && self.src.starts_with("#!") {
+            let end = self.src.find('\n').unwrap_or(self.src.len());
+            self.emit_to(end);
+            self.last_significant = None;
+        }
+        while self.pos < self.bytes.len() {
+            self.step();
+        }
+        if self.line_stripped {
+            let line = &self.out[self.line_start..];
+            let kept = if line.trim().is_empty() { 0 } else { line.trim_end().len() };
+            self.out.truncate(self.line_start + kept);
+        }
+    }
+
+    fn step(&mut self) {
+        let byte = self.bytes[self.pos];
+        let rest = self.rest();
+
+        if byte == b'\n' {
+            self.newline();
+            return;
+        }
+        if let Some((open, _)) = self.syntax.block
+            && rest.starts_with(open)
+        {
+            return self.block_comment();
+        }
+        if let Some(marker) = self.syntax.line.iter().find(|m| rest.starts_with(**m))
+            && self.is_line_comment(marker)
+        {
+            return self.line_comment();
+        }
+        if self.syntax.triple_quotes && (rest.starts_with("\"\"\"") || rest.starts_with("'''")) {
+            return self.triple_quoted();
+        }
+        if self.syntax.rust_raw_strings && self.rust_raw_string() {
+            return;
+        }
+        if self.syntax.cpp_raw_strings && self.cpp_raw_string() {
+            return;
+        }
+        if let Some(operator) = self.syntax.heredoc
+            && rest.starts_with(operator)
+            && self.heredoc(operator)
+        {
+            return;
+        }
+        if self.syntax.char_literals && byte == b'\'' {
+            return self.char_literal();
+        }
+        if self.syntax.quotes.contains(&byte) {
+            return self.string(byte, true);
+        }
+        if self.syntax.raw_quotes.contains(&byte) {
+            return self.string(byte, false);
+        }
+        if self.syntax.regex_literals && byte == b'/' && self.regex_literal() {
+            return;
+        }
+        let next = rest.char_indices().nth(1).map_or(self.src.len(), |(i, _)| self.pos + i);
+        self.emit_to(next);
+    }
+
+    fn is_line_comment(&self, marker: &str) -> bool {
+        let previous = self.pos.checked_sub(1).map(|i| self.bytes[i]);
+        if marker == "#" {
+            if self.syntax.hash_attributes && self.peek(1) == Some(b'[') {
+                return false;
+            }
+            if self.syntax.hash_at_word_start
+                && previous.is_some_and(|b| !b.is_ascii_whitespace() && !b";|&(".contains(&b))
+            {
+                return false;
+            }
+        }
+        !(marker == "//" && self.syntax.url_guard && previous == Some(b':'))
+    }
+
+    fn is_doc(&self) -> bool {
+        let rest = self.rest();
+        self.syntax.doc.iter().any(|doc| {
+            rest.starts_with(doc) && {
+                // `////` and `/***` are plain comments, `/**/` is empty
+                let last = doc.as_bytes()[doc.len() - 1];
+                let next = self.peek(doc.len());
+                !(matches!(last, b'/' | b'*') && next.is_some_and(|n| n == last || (last == b'*' && n == b'/')))
+            }
+        })
+    }
+
+    fn line_comment(&mut self) {
+        let end = self.rest().find('\n').map_or(self.src.len(), |i| self.pos + i);
+        // Keep the `\r` of a CRLF line ending
+        let end = if end > self.pos && self.bytes[end - 1] == b'\r' { end - 1 } else { end };
+        if self.keep_comments || (self.is_doc() && !self.docstrings) {
+            self.keep_comment(end);
+        } else {
+            self.pos = end;
+            self.line_stripped = true;
+        }
+    }
+
+    fn block_comment(&mut self) {
+        let (open, close) = self.syntax.block.unwrap_or(("/*", "*/"));
+        let start = self.pos;
+        let keep = self.keep_comments || (self.is_doc() && !self.docstrings);
+        let mut depth = 0;
+        let mut i = self.pos;
+        let end = loop {
+            let rest = &self.src[i..];
+            if rest.is_empty() {
+                break self.src.len();
+            }
+            if rest.starts_with(open) && (depth == 0 || self.syntax.nested_blocks) {
+                depth += 1;
+                i += open.len();
+            } else if rest.starts_with(close) {
+                depth -= 1;
+                i += close.len();
+                if depth == 0 {
+                    break i;
+                }
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        };
+        if keep {
+            return self.keep_comment(end);
+        }
+        self.line_stripped = true;
+        match self.src[start..end].find('\n') {
+            // A multi-line comment becomes a single line break
+            Some(newline) => {
+                self.pos = start + newline;
+                self.newline();
+                self.pos = end;
+                self.line_stripped = true;
+            }
+            None => {
+                self.pos = end;
+                // `a /* x */ b` becomes `a b`
+                if self.out.ends_with([' ', '\t']) {
+                    let spaces = self.rest().len() - self.rest().trim_start_matches([' ', '\t']).len();
+                    self.pos += spaces;
+                }
+            }
+        }
+    }
+
+    /// Scans a string literal starting at the opening quote.
+    fn string(&mut self, quote: u8, escapes: bool) {
+        let mut i = self.pos + 1;
+        while i < self.bytes.len() {
+            match self.bytes[i] {
+                b'\\' if escapes => i += 2,
+                b'\n' if self.syntax.single_line_strings && quote != b'`' => break,
+                b if b == quote => {
+                    i += 1;
+                    break;
+                }
+                _ => i += 1,
+            }
+        }
+        let end = i.min(self.bytes.len());
+        self.emit_literal(end);
+    }
+
+    /// `'a'`, `'\n'` and `'\u{1F600}'` are character literals; `'a` (a Rust lifetime or
+    /// label) and `1'000` (a C++ digit separator) are code.
+    fn char_literal(&mut self) {
+        let after_ident = self.pos > 0 && is_ident(self.bytes[self.pos - 1]);
+        // `b'x'` in Rust, `L'x'` / `u8'x'` in C and C++
+        if !after_ident || self.after_prefix(&["b", "u8", "u", "U", "L"]) {
+            if self.peek(1) == Some(b'\\') {
+                return self.string(b'\'', true);
+            }
+            if let Some(c) = self.rest()[1..].chars().next()
+                && c != '\''
+                && self.rest()[1 + c.len_utf8()..].starts_with('\'')
+            {
+                let end = self.pos + 2 + c.len_utf8();
+                return self.emit_literal(end);
+            }
+        }
+        self.emit_to(self.pos + 1);
+    }
+
+    fn triple_quoted(&mut self) {
+        let delimiter = &self.rest()[..3];
+        let end = self.rest()[3..]
+            .find(delimiter)
+            .map_or(self.src.len(), |i| self.pos + 3 + i + 3);
+        // A docstring is a string statement at the start of a module or right after a
+        // `def`/`class` header
+        let at_statement_start = self.out[self.line_start..].trim().is_empty();
+        let after_header = matches!(self.last_significant, None | Some(b':'));
+        let alone = self.src[end..].split('\n').next().unwrap_or("").trim().is_empty();
+        if self.docstrings && at_statement_start && after_header && alone {
+            self.pos = end;
+            self.line_stripped = true;
+        } else {
+            self.emit_literal(end);
+        }
+    }
+
+    fn rust_raw_string(&mut self) -> bool {
+        let rest = self.rest();
+        let prefix = if rest.starts_with("br") { 2 } else if rest.starts_with('r') { 1 } else { return false };
+        if self.pos > 0 && is_ident(self.bytes[self.pos - 1]) {
+            return false;
+        }
+        let hashes = rest[prefix..].bytes().take_while(|&b| b == b'#').count();
+        if !rest[prefix + hashes..].starts_with('"') {
+            return false;
+        }
+        let close = format!("\"{}", "#".repeat(hashes));
+        let body = prefix + hashes + 1;
+        let end = rest[body..].find(&close).map_or(self.src.len(), |i| self.pos + body + i + close.len());
+        self.emit_literal(end);
+        true
+    }
+
+    fn cpp_raw_string(&mut self) -> bool {
+        let rest = self.rest();
+        let after_ident = self.pos > 0 && is_ident(self.bytes[self.pos - 1]);
+        if !rest.starts_with("R\"") || (after_ident && !self.after_prefix(&["u8", "u", "U", "L"])) {
+            return false;
+        }
+        let Some(paren) = rest[2..].find('(') else { return false };
+        let delimiter = &rest[2..2 + paren];
+        if delimiter.len() > 16 || delimiter.contains(|c: char| c.is_whitespace() || c == '\\') {
+            return false;
+        }
+        let close = format!("){}\"", delimiter);
+        let body = 2 + paren + 1;
+        let end = rest[body..].find(&close).map_or(self.src.len(), |i| self.pos + body + i + close.len());
+        self.emit_literal(end);
+        true
+    }
+
+    /// The code before `self.pos` ends with one of the literal prefixes `prefixes`, as a
+    /// whole word.
+    fn after_prefix(&self, prefixes: &[&str]) -> bool {
+        let before = &self.src[..self.pos];
+        prefixes.iter().any(|p| {
+            before.ends_with(p) && before[..before.len() - p.len()].bytes().last().is_none_or(|b| !is_ident(b))
+        })
+    }
+
+    /// Records a here-document started at `self.pos`; its body is copied verbatim after
+    /// the current line.
+    fn heredoc(&mut self, operator: &str) -> bool {
+        let rest = self.rest();
+        let after = &rest[operator.len()..];
+        // `<<<` is a here-string in shell
+        if after.starts_with('<') {
+            return false;
+        }
+        let spec = after.strip_prefix('-').unwrap_or(after).trim_start_matches([' ', '\t']);
+        let spec = spec.trim_start_matches(['\'', '"']);
+        let word: String = spec.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        if word.is_empty() {
+            return false;
+        }
+        let word_end = self.pos + rest.len() - spec.len() + word.len();
+        let end = if self.src[word_end..].starts_with(['\'', '"']) { word_end + 1 } else { word_end };
+        self.emit_to(end);
+        self.heredocs.push(word);
+        true
+    }
+
+    fn copy_heredocs(&mut self) {
+        for terminator in std::mem::take(&mut self.heredocs) {
+            let start = self.out.len();
+            while self.pos < self.bytes.len() {
+                let line_end = self.rest().find('\n').map_or(self.src.len(), |i| self.pos + i + 1);
+                let line = &self.src[self.pos..line_end];
+                self.out.push_str(line);
+                self.pos = line_end;
+                let trimmed = line.trim();
+                if trimmed
+                    .strip_prefix(terminator.as_str())
+                    .is_some_and(|tail| !tail.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+                {
+                    break;
+                }
+            }
+            self.literals.push(start..self.out.len());
+            self.line_start = self.out.len();
+        }
+    }
+
+    /// A `/` where an operand is expected starts a regular expression literal.
+    fn regex_literal(&mut self) -> bool {
+        let expects_operand = self
+            .last_significant
+            .is_none_or(|b| b"(,=:[!&|?{};+-*%<>~^".contains(&b));
+        if !expects_operand {
+            return false;
+        }
+        let mut i = self.pos + 1;
+        let mut in_class = false;
+        while i < self.bytes.len() {
+            match self.bytes[i] {
+                b'\\' => i += 2,
+                b'\n' => return false,
+                b'[' => {
+                    in_class = true;
+                    i += 1;
+                }
+                b']' => {
+                    in_class = false;
+                    i += 1;
+                }
+                b'/' if !in_class => {
+                    self.emit_literal(i + 1);
+                    return true;
+                }
+                _ => i += 1,
+            }
+        }
+        false
+    }
+}
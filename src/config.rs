@@ -1,3 +1,11 @@
+use crate::encoding::EncodingPolicy;
+use crate::normalize::Normalization;
+use crate::ordering::SortOrder;
+use crate::patterns::PatternTarget;
+use crate::redact::RedactRule;
+use crate::render::OutputFormat;
+use crate::tokens::Tokenizer;
+use crate::truncate::{TruncateRule, Truncation};
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -12,20 +20,48 @@ pub struct ConfigFile {
     pub allowed_filenames: Option<Vec<String>>,
     pub max_size: Option<f64>,
     pub markdown: Option<bool>,
+    pub format: Option<OutputFormat>,
+    /// Template file for custom output (see `TemplateRenderer`); relative paths are
+    /// resolved against the directory of the config file.
+    pub template: Option<PathBuf>,
+    pub tokenizer: Option<Tokenizer>,
     pub gpt4_tokens: Option<bool>,
+    pub max_tokens: Option<usize>,
     pub include_git_changes: Option<bool>,
     pub no_staged_diff: Option<bool>,
     pub no_unstaged_diff: Option<bool>,
+    pub git_log: Option<usize>,
+    pub git_log_stat: Option<bool>,
+    pub git_diff_base: Option<String>,
     pub include_dirs: Option<Vec<PathBuf>>,
     pub exclude_dirs: Option<Vec<PathBuf>>,
     pub exclude_patterns: Option<Vec<String>>,
     pub include_patterns: Option<Vec<String>>,
+    pub pattern_target: Option<PatternTarget>,
+    pub normalize: Option<Normalization>,
+    pub strip_comments: Option<bool>,
+    pub strip_docstrings: Option<bool>,
+    pub outline: Option<bool>,
+    pub outline_globs: Option<Vec<String>>,
+    pub full_globs: Option<Vec<String>>,
+    pub truncate: Option<Truncation>,
+    pub truncate_globs: Option<Vec<String>>,
+    pub truncate_rules: Option<Vec<TruncateRule>>,
     pub exclude_globs: Option<Vec<String>>,
     pub include_globs: Option<Vec<String>>,
     pub exclude_node_modules: Option<bool>,
     pub exclude_build_dirs: Option<bool>,
     pub exclude_hidden_dirs: Option<bool>,
     pub max_depth: Option<usize>,
+    pub sort: Option<SortOrder>,
+    pub tree: Option<bool>,
+    pub tree_excluded: Option<bool>,
+    pub encoding_fallback: Option<EncodingPolicy>,
+    pub list_skipped: Option<bool>,
+    pub redact_secrets: Option<bool>,
+    pub fail_on_secrets: Option<bool>,
+    /// `[[redact]]` rules applied on top of the built-in secret detectors.
+    pub redact: Option<Vec<RedactRule>>,
 
     // Custom profiles section: [profiles.my-profile]
     pub profiles: Option<HashMap<String, CustomProfile>>,
@@ -42,20 +78,36 @@ pub struct CustomProfile {
     pub allowed_filenames: Option<Vec<String>>,
     pub max_size: Option<f64>,
     pub markdown: Option<bool>,
+    pub tokenizer: Option<Tokenizer>,
     pub gpt4_tokens: Option<bool>,
     pub include_git_changes: Option<bool>,
     pub no_staged_diff: Option<bool>,
     pub no_unstaged_diff: Option<bool>,
+    pub git_log: Option<usize>,
+    pub git_log_stat: Option<bool>,
+    pub git_diff_base: Option<String>,
     pub include_dirs: Option<Vec<PathBuf>>,
     pub exclude_dirs: Option<Vec<PathBuf>>,
     pub exclude_patterns: Option<Vec<String>>,
     pub include_patterns: Option<Vec<String>>,
+    pub pattern_target: Option<PatternTarget>,
+    pub normalize: Option<Normalization>,
+    pub strip_comments: Option<bool>,
+    pub strip_docstrings: Option<bool>,
+    pub outline: Option<bool>,
+    pub outline_globs: Option<Vec<String>>,
+    pub full_globs: Option<Vec<String>>,
+    pub truncate: Option<Truncation>,
+    pub truncate_globs: Option<Vec<String>>,
+    pub truncate_rules: Option<Vec<TruncateRule>>,
     pub exclude_globs: Option<Vec<String>>,
     pub include_globs: Option<Vec<String>>,
     pub exclude_node_modules: Option<bool>,
     pub exclude_build_dirs: Option<bool>,
     pub exclude_hidden_dirs: Option<bool>,
     pub max_depth: Option<usize>,
+    /// `[[profiles.<name>.redact]]` rules, added to those of the parent profile.
+    pub redact: Option<Vec<RedactRule>>,
 }
 
 /// Loads the configuration file from the given path or defaults to .flattener.toml or flattener.toml
@@ -76,8 +128,14 @@ pub fn load_config(config_path: &Option<PathBuf>) -> Result<Option<ConfigFile>>
     if path.exists() {
         let content = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-        let config: ConfigFile = toml::from_str(&content)
+        let mut config: ConfigFile = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        if let Some(template) = &config.template
+            && template.is_relative()
+            && let Some(config_dir) = path.parent()
+        {
+            config.template = Some(config_dir.join(template));
+        }
         return Ok(Some(config));
     }
     
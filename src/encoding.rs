@@ -0,0 +1,111 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Name recorded for content decoded with [`EncodingPolicy::Lossy`].
+pub(crate) const LOSSY_UTF8: &str = "UTF-8 (lossy)";
+
+/// What to do with a file that is not valid UTF-8 and has no byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingPolicy {
+    /// Guess the encoding (Windows-1252, Shift_JIS, UTF-16 without BOM, ...) and
+    /// transcode to UTF-8.
+    #[default]
+    Transcode,
+    /// Keep the bytes as UTF-8, replacing invalid sequences with U+FFFD.
+    Lossy,
+    /// Leave the file out (listed with `--list-skipped`).
+    Skip,
+    /// Fail the run (skip with a warning in parallel mode).
+    Error,
+}
+
+impl FromStr for EncodingPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "transcode" => Ok(EncodingPolicy::Transcode),
+            "lossy" => Ok(EncodingPolicy::Lossy),
+            "skip" => Ok(EncodingPolicy::Skip),
+            "error" => Ok(EncodingPolicy::Error),
+            other => Err(format!(
+                "unknown encoding policy '{}' (expected transcode, lossy, skip or error)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EncodingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EncodingPolicy::Transcode => "transcode",
+            EncodingPolicy::Lossy => "lossy",
+            EncodingPolicy::Skip => "skip",
+            EncodingPolicy::Error => "error",
+        })
+    }
+}
+
+/// UTF-16 without a byte order mark: ASCII-heavy text has a NUL in every other byte.
+pub(crate) fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(1024) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_nuls = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_nuls = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    // Nearly every code unit of one half is NUL and almost none of the other
+    if odd_nuls * 10 >= pairs * 9 && even_nuls * 10 <= pairs {
+        Some(UTF_16LE)
+    } else if even_nuls * 10 >= pairs * 9 && odd_nuls * 10 <= pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Whether the content starts with a UTF-16 byte order mark or looks like BOM-less UTF-16,
+/// so the binary sniff should not count its NUL bytes.
+pub(crate) fn looks_like_utf16(bytes: &[u8]) -> bool {
+    matches!(Encoding::for_bom(bytes), Some((enc, _)) if enc != UTF_8) || sniff_utf16(bytes).is_some()
+}
+
+/// Decodes file content to UTF-8. Returns the text and the name of the encoding it was
+/// read as, or `None` when the content is not UTF-8 and `policy` is `Skip` or `Error`.
+///
+/// A byte order mark always wins and is kept as a leading U+FEFF, which
+/// [`Normalization::strip_bom`](crate::Normalization::strip_bom) removes; otherwise valid UTF-8 is taken as is
+/// (unless it looks like BOM-less UTF-16 and `policy` is `Transcode`).
+pub(crate) fn decode(bytes: &[u8], policy: EncodingPolicy) -> Option<(String, &'static str)> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Some((format!("\u{FEFF}{}", text), encoding.name()));
+    }
+    // ASCII-only UTF-16 is also valid UTF-8, so it has to be recognised first
+    if policy == EncodingPolicy::Transcode
+        && let Some(encoding) = sniff_utf16(bytes)
+    {
+        let (text, _) = encoding.decode_without_bom_handling(bytes);
+        return Some((text.into_owned(), encoding.name()));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Some((text.to_string(), UTF_8.name()));
+    }
+    match policy {
+        EncodingPolicy::Transcode => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, true);
+            let encoding = detector.guess(None, false);
+            let (text, _) = encoding.decode_without_bom_handling(bytes);
+            Some((text.into_owned(), encoding.name()))
+        }
+        EncodingPolicy::Lossy => Some((String::from_utf8_lossy(bytes).into_owned(), LOSSY_UTF8)),
+        EncodingPolicy::Skip | EncodingPolicy::Error => None,
+    }
+}
@@ -0,0 +1,208 @@
+use crate::filters::{build_walker, walker_exclusion, Selector, SkipReason, IGNORE_FILE_NAME};
+use crate::git::find_git_root;
+use crate::options::FlattenOptions;
+use anyhow::{Context, Result};
+use ignore::gitignore::Gitignore;
+use ignore::Match;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ignore files in order of precedence, as applied by the walker.
+const IGNORE_FILES: &[&str] = &[IGNORE_FILE_NAME, ".ignore", ".gitignore"];
+
+/// Whether an entry goes into the output, and the rule that decided it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Included(String),
+    Excluded(SkipReason),
+}
+
+/// The decision made for one file or directory under a target directory.
+///
+/// Directories only appear when the walker does not enter them; their contents are
+/// excluded by the same rule and not listed.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub path: PathBuf,
+    /// Path relative to the target directory it was found in.
+    pub relative_path: PathBuf,
+    pub is_dir: bool,
+    pub decision: Decision,
+}
+
+impl Explanation {
+    pub fn is_included(&self) -> bool {
+        matches!(self.decision, Decision::Included(_))
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.relative_path.to_string_lossy().replace('\\', "/");
+        let slash = if self.is_dir { "/" } else { "" };
+        match &self.decision {
+            Decision::Included(rule) => write!(f, "include  {}{}: {}", path, slash, rule),
+            Decision::Excluded(reason) => write!(f, "exclude  {}{}: {}", path, slash, reason),
+        }
+    }
+}
+
+/// Ignore-file matchers, loaded lazily per directory.
+struct IgnoreRules {
+    git_root: Option<PathBuf>,
+    matchers: HashMap<(PathBuf, &'static str), Option<Gitignore>>,
+}
+
+impl IgnoreRules {
+    fn new(start_dir: &Path) -> Self {
+        Self {
+            git_root: find_git_root(start_dir).ok().flatten(),
+            matchers: HashMap::new(),
+        }
+    }
+
+    fn matcher(&mut self, dir: &Path, file_name: &'static str) -> Option<&Gitignore> {
+        self.matchers
+            .entry((dir.to_path_buf(), file_name))
+            .or_insert_with(|| {
+                let file = dir.join(file_name);
+                file.is_file().then(|| Gitignore::new(&file).0)
+            })
+            .as_ref()
+    }
+
+    /// The ignore-file line that excludes `path`, if any. Like the walker, nearer
+    /// directories override farther ones and `.flattenerignore` overrides `.ignore`,
+    /// which overrides `.gitignore` (only honoured inside a git repository).
+    fn exclusion(&mut self, path: &Path, is_dir: bool) -> Option<SkipReason> {
+        let parent = path.parent()?;
+        for &file_name in IGNORE_FILES {
+            let is_gitignore = file_name == ".gitignore";
+            let git_root = self.git_root.clone();
+            if is_gitignore && git_root.is_none() {
+                continue;
+            }
+            for dir in parent.ancestors() {
+                if is_gitignore && git_root.as_deref().is_some_and(|root| !dir.starts_with(root)) {
+                    break;
+                }
+                let Some(matcher) = self.matcher(dir, file_name) else { continue };
+                match matcher.matched(path, is_dir) {
+                    Match::None => continue,
+                    Match::Whitelist(_) => return None,
+                    Match::Ignore(glob) => {
+                        let file = dir.join(file_name);
+                        let line = ignore_line(&file, glob.original());
+                        return Some(SkipReason::IgnoreFile { file, line, pattern: glob.original().to_string() });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 1-based line of `pattern` in an ignore file.
+fn ignore_line(file: &Path, pattern: &str) -> Option<usize> {
+    let content = fs::read_to_string(file).ok()?;
+    content.lines().position(|line| line.trim() == pattern.trim()).map(|i| i + 1)
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') && name != "." && name != ".."
+}
+
+struct ExplainWalk<'a> {
+    options: &'a FlattenOptions,
+    selector: &'a Selector<'a>,
+    base_dir: &'a Path,
+    ignores: IgnoreRules,
+    /// Files the real walker yields, to catch rules not modelled here
+    /// (global gitignore, `.git/info/exclude`).
+    walked: HashSet<PathBuf>,
+    out: Vec<Explanation>,
+}
+
+impl ExplainWalk<'_> {
+    fn push(&mut self, path: &Path, is_dir: bool, decision: Decision) {
+        self.out.push(Explanation {
+            path: path.to_path_buf(),
+            relative_path: path.strip_prefix(self.base_dir).unwrap_or(path).to_path_buf(),
+            is_dir,
+            decision,
+        });
+    }
+
+    fn walk_dir(&mut self, dir: &Path, depth: usize) -> Result<()> {
+        let mut entries: Vec<_> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+            .filter_map(Result::ok)
+            .collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                continue;
+            }
+            // Symlinks are not followed, as in the walker
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+
+            if depth + 1 > self.options.max_depth {
+                self.push(&path, is_dir, Decision::Excluded(SkipReason::MaxDepth(self.options.max_depth)));
+                continue;
+            }
+            let walker_reason = match self.ignores.exclusion(&path, is_dir) {
+                Some(reason) => Some(reason),
+                None if is_hidden(&name) => Some(SkipReason::Hidden(name.clone())),
+                None => walker_exclusion(&name, self.options),
+            };
+            if let Some(reason) = walker_reason {
+                self.push(&path, is_dir, Decision::Excluded(reason));
+                continue;
+            }
+
+            if is_dir {
+                self.walk_dir(&path, depth + 1)?;
+                continue;
+            }
+            let decision = match self.selector.check(&path, self.base_dir) {
+                Ok(_) if !self.walked.contains(&path) => Decision::Excluded(SkipReason::GitExclude),
+                Ok(rule) => Decision::Included(rule),
+                Err(reason) => Decision::Excluded(reason),
+            };
+            self.push(&path, false, decision);
+        }
+        Ok(())
+    }
+}
+
+/// Explains the decision for every entry under the target directories of `options`
+/// (already resolved against their profile), in walk order.
+pub(crate) fn explain(options: &FlattenOptions) -> Result<Vec<Explanation>> {
+    let selector = Selector::new(options)?;
+    let mut out = Vec::new();
+    for start_dir in &options.target_dirs {
+        let start_dir = fs::canonicalize(start_dir)
+            .with_context(|| format!("Failed to canonicalize path: {}", start_dir.display()))?;
+        let walked = build_walker(&start_dir, options)
+            .build()
+            .filter_map(Result::ok)
+            .map(|entry| entry.into_path())
+            .collect();
+        let mut walk = ExplainWalk {
+            options,
+            selector: &selector,
+            base_dir: &start_dir,
+            ignores: IgnoreRules::new(&start_dir),
+            walked,
+            out: Vec::new(),
+        };
+        walk.walk_dir(&start_dir, 0)?;
+        out.append(&mut walk.out);
+    }
+    Ok(out)
+}
@@ -0,0 +1,426 @@
+use crate::changed::ChangedFiles;
+use crate::encoding::looks_like_utf16;
+use crate::options::FlattenOptions;
+use crate::patterns::PatternFilter;
+use crate::truncate::truncation_for;
+use anyhow::Result;
+use glob::Pattern;
+use ignore::WalkBuilder;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Per-directory ignore file with gitignore syntax.
+pub(crate) const IGNORE_FILE_NAME: &str = ".flattenerignore";
+
+pub(crate) fn build_walker(start_dir: &Path, options: &FlattenOptions) -> WalkBuilder {
+    let mut walker = WalkBuilder::new(start_dir);
+    walker.max_depth(Some(options.max_depth));
+    // Sorting entries by name makes the walk order independent of the filesystem
+    walker.sort_by_file_name(|a, b| a.cmp(b));
+    // .flattenerignore files work like .gitignore: one per directory (parents of the
+    // target included), with negation, anchored and directory-only patterns
+    walker.add_custom_ignore_filename(IGNORE_FILE_NAME);
+
+    let options = options.clone();
+    walker.filter_entry(move |entry| walker_exclusion(&entry.file_name().to_string_lossy(), &options).is_none());
+
+    walker
+}
+
+/// Entries the walker never yields (and directories it never enters), by name.
+pub(crate) fn walker_exclusion(name: &str, options: &FlattenOptions) -> Option<SkipReason> {
+    let rule = if options.exclude_node_modules && name == "node_modules" {
+        "exclude_node_modules"
+    } else if options.exclude_build_dirs && matches!(name, "target" | "build" | "dist") {
+        "exclude_build_dirs"
+    } else if options.exclude_hidden_dirs && name.starts_with('.') {
+        "exclude_hidden_dirs"
+    } else if name == "wp-admin" || name == "wp-includes" {
+        // Always filter specific WP dirs to avoid massive dumps unless explicitly crawled
+        "the built-in WordPress admin/includes exclusion"
+    } else {
+        return None;
+    };
+    Some(SkipReason::WalkerExcluded { name: name.to_string(), rule })
+}
+
+/// Why a walked file was left out of the output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// Not a regular file.
+    NotAFile,
+    /// Deeper below the target directory than `max_depth`.
+    MaxDepth(usize),
+    /// Inside (or itself) a directory the walker never enters.
+    WalkerExcluded { name: String, rule: &'static str },
+    /// Hidden file or directory; the walker skips these.
+    Hidden(String),
+    /// Matched a line of an ignore file (`.flattenerignore`, `.ignore` or `.gitignore`).
+    IgnoreFile { file: PathBuf, line: Option<usize>, pattern: String },
+    /// Ignored through the global gitignore or `.git/info/exclude`.
+    GitExclude,
+    ExcludeDir(PathBuf),
+    NotInIncludeDirs,
+    ExcludeGlob(String),
+    NoIncludeGlobMatch,
+    WpExcludedPlugin(String),
+    /// Extension on the built-in list of binary formats.
+    BinaryExtension(String),
+    /// NUL or control bytes in the first KiB.
+    BinaryContent,
+    /// WordPress strict mode (`wp_include_only_plugins` / `wp_include_theme`) and not in scope.
+    WpStrictMode,
+    WpCoreFile(String),
+    /// Neither the extension nor the file name is allowed.
+    NotAllowed,
+    ExcludePattern(String),
+    NoIncludePatternMatch,
+    TooLarge { size: u64, limit: u64 },
+    /// Content is not valid UTF-8.
+    NotUtf8,
+    /// Reading the file failed.
+    Unreadable(String),
+    /// Not changed since the `changed_since` ref (or in the working tree, for
+    /// `changed_only`), nor next to a changed file.
+    Unchanged(String),
+}
+
+impl SkipReason {
+    /// Short machine-readable name of the rule, e.g. `binary_extension`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::NotAFile => "not_a_file",
+            SkipReason::MaxDepth(_) => "max_depth",
+            SkipReason::WalkerExcluded { .. } => "walker_excluded",
+            SkipReason::Hidden(_) => "hidden",
+            SkipReason::IgnoreFile { .. } => "ignore_file",
+            SkipReason::GitExclude => "git_exclude",
+            SkipReason::ExcludeDir(_) => "exclude_dir",
+            SkipReason::NotInIncludeDirs => "not_in_include_dirs",
+            SkipReason::ExcludeGlob(_) => "exclude_glob",
+            SkipReason::NoIncludeGlobMatch => "no_include_glob_match",
+            SkipReason::WpExcludedPlugin(_) => "wp_excluded_plugin",
+            SkipReason::BinaryExtension(_) => "binary_extension",
+            SkipReason::BinaryContent => "binary_content",
+            SkipReason::WpStrictMode => "wp_strict_mode",
+            SkipReason::WpCoreFile(_) => "wp_core_file",
+            SkipReason::NotAllowed => "not_allowed",
+            SkipReason::ExcludePattern(_) => "exclude_pattern",
+            SkipReason::NoIncludePatternMatch => "no_include_pattern_match",
+            SkipReason::TooLarge { .. } => "too_large",
+            SkipReason::NotUtf8 => "not_utf8",
+            SkipReason::Unreadable(_) => "unreadable",
+            SkipReason::Unchanged(_) => "unchanged",
+        }
+    }
+
+    /// Whether the file was wanted but left out because of its content, size or a read
+    /// failure, rather than by a selection rule; these are listed with `list_skipped`.
+    pub(crate) fn is_listed_as_skipped(&self) -> bool {
+        matches!(
+            self,
+            SkipReason::BinaryExtension(_)
+                | SkipReason::BinaryContent
+                | SkipReason::TooLarge { .. }
+                | SkipReason::NotUtf8
+                | SkipReason::Unreadable(_)
+        )
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NotAFile => write!(f, "not a regular file"),
+            SkipReason::MaxDepth(depth) => write!(f, "deeper than max_depth {}", depth),
+            SkipReason::WalkerExcluded { name, rule } => write!(f, "directory '{}' skipped by {}", name, rule),
+            SkipReason::Hidden(name) => write!(f, "hidden entry '{}' skipped by the walker", name),
+            SkipReason::IgnoreFile { file, line: Some(line), pattern } => {
+                write!(f, "ignored by {} line {}: {}", file.display(), line, pattern)
+            }
+            SkipReason::IgnoreFile { file, line: None, pattern } => {
+                write!(f, "ignored by {}: {}", file.display(), pattern)
+            }
+            SkipReason::GitExclude => write!(f, "ignored by the global gitignore or .git/info/exclude"),
+            SkipReason::ExcludeDir(dir) => write!(f, "inside exclude_dirs entry '{}'", dir.display()),
+            SkipReason::NotInIncludeDirs => write!(f, "not inside any include_dirs entry"),
+            SkipReason::ExcludeGlob(glob) => write!(f, "matches exclude glob '{}'", glob),
+            SkipReason::NoIncludeGlobMatch => write!(f, "matches no include glob"),
+            SkipReason::WpExcludedPlugin(slug) => write!(f, "in excluded WordPress plugin '{}'", slug),
+            SkipReason::BinaryExtension(ext) => write!(f, "binary file (extension .{})", ext),
+            SkipReason::BinaryContent => write!(f, "binary file (control bytes in the first 1 KiB)"),
+            SkipReason::WpStrictMode => {
+                write!(f, "outside wp_include_only_plugins / wp_include_theme (WordPress strict mode)")
+            }
+            SkipReason::WpCoreFile(name) => write!(f, "WordPress core file '{}'", name),
+            SkipReason::NotAllowed => write!(f, "extension and file name not in the allow-list"),
+            SkipReason::ExcludePattern(pattern) => write!(f, "matches exclude pattern '{}'", pattern),
+            SkipReason::NoIncludePatternMatch => write!(f, "matches no include pattern"),
+            SkipReason::TooLarge { size, limit } => {
+                write!(f, "{} bytes exceeds the size limit of {} bytes", size, limit)
+            }
+            SkipReason::NotUtf8 => write!(f, "content is not valid UTF-8"),
+            SkipReason::Unreadable(error) => write!(f, "could not be read: {}", error),
+            SkipReason::Unchanged(label) => write!(f, "not changed {}", label),
+        }
+    }
+}
+
+const CORE_WP_FILES: &[&str] = &[
+    "xmlrpc.php", "wp-activate.php", "wp-cron.php", "wp-load.php",
+    "wp-blog-header.php", "wp-settings.php", "wp-login.php", "wp-signup.php",
+    "wp-trackback.php", "wp-comments-post.php", "wp-links-opml.php", "wp-mail.php",
+];
+
+/// Checks the path-based rules in order and returns the first one that excludes `path`.
+pub(crate) fn path_exclusion(path: &Path, options: &FlattenOptions, base_dir: &Path) -> Option<SkipReason> {
+    if path.is_dir() { return Some(SkipReason::NotAFile); }
+
+    let relative_path = match path.strip_prefix(base_dir) {
+        Ok(p) => p,
+        Err(_) => path,
+    };
+
+    // Directory Exclusions
+    if let Some(exclude_dirs) = &options.exclude_dirs {
+        for exclude_dir in exclude_dirs {
+            if relative_path.starts_with(exclude_dir) {
+                return Some(SkipReason::ExcludeDir(exclude_dir.clone()));
+            }
+        }
+    }
+
+    // Directory Inclusions (Exclusive)
+    if let Some(include_dirs) = &options.include_dirs
+        && !include_dirs.iter().any(|dir| relative_path.starts_with(dir))
+    {
+        return Some(SkipReason::NotInIncludeDirs);
+    }
+
+    // Exclude Globs
+    if let Some(exclude_globs) = &options.exclude_globs {
+        for pattern in exclude_globs {
+            // Check matches against OS path and forward-slash normalized path
+            if match_glob(pattern, relative_path) {
+                return Some(SkipReason::ExcludeGlob(pattern.clone()));
+            }
+        }
+    }
+
+    // Include Globs
+    if let Some(include_globs) = &options.include_globs
+        && !include_globs.iter().any(|pattern| match_glob(pattern, relative_path))
+    {
+        return Some(SkipReason::NoIncludeGlobMatch);
+    }
+
+    // WordPress-specific Exclusions
+    if let Some(excludes) = &options.wp_exclude_plugins
+        && let Ok(rel) = path.strip_prefix(base_dir)
+    {
+        let rel_str = rel.to_string_lossy().to_lowercase();
+        for raw in excludes {
+            let slug = raw.split('/').next().unwrap_or(raw).to_lowercase();
+            let plugin_prefix = format!("wp-content/plugins/{}", slug);
+            if rel_str.starts_with(&plugin_prefix) {
+                return Some(SkipReason::WpExcludedPlugin(slug));
+            }
+        }
+    }
+
+    // WordPress Inclusion Logic (Strict Mode)
+    if options.profile.as_deref() == Some("wordpress")
+        && (options.wp_include_only_plugins.is_some() || options.wp_include_theme.is_some())
+        && let Ok(rel) = path.strip_prefix(base_dir)
+    {
+        let rel_str_lower = rel.to_string_lossy().to_lowercase();
+        if rel_str_lower == "wp-config.php" { return None; }
+
+        if let Some(includes) = &options.wp_include_only_plugins {
+            for raw in includes {
+                let slug = raw.split('/').next().unwrap_or(raw).to_lowercase();
+                let prefix = format!("wp-content/plugins/{}", slug);
+                if rel_str_lower.starts_with(&prefix) { return None; }
+            }
+        }
+
+        if let Some(theme) = &options.wp_include_theme {
+             let prefix = format!("wp-content/themes/{}", theme.to_lowercase());
+             if rel_str_lower.starts_with(&prefix) { return None; }
+        }
+        return Some(SkipReason::WpStrictMode); // Strict mode active and no match
+    }
+
+    // Core WP File Exclusion
+    if let Some(file_name) = path.file_name().and_then(|n| n.to_str())
+        && CORE_WP_FILES.contains(&file_name)
+    {
+        return Some(SkipReason::WpCoreFile(file_name.to_string()));
+    }
+
+    None
+}
+
+/// Allowed if extension matches OR filename matches OR include_globs are present; returns
+/// a description of the rule that allowed the file.
+///
+/// include_globs matching happens in `path_exclusion`. To support profiles that
+/// *only* have include_globs (no extensions), we must be permissive here if include_globs
+/// are present.
+pub(crate) fn allow_list_match(
+    path: &Path,
+    extensions: &HashSet<String>,
+    allowed_filenames: &HashSet<String>,
+    options: &FlattenOptions,
+) -> Option<String> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let extension = format!(".{}", path.extension().unwrap_or_default().to_string_lossy());
+
+    if extensions.contains(&extension) {
+        Some(format!("extension {} is allowed", extension))
+    } else if allowed_filenames.contains(file_name.as_ref()) {
+        Some(format!("file name {} is allowed", file_name))
+    } else if options.include_globs.is_some() {
+        Some("matches an include glob".to_string())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn match_glob(pattern: &str, path: &Path) -> bool {
+    let pat_os = pattern.replace('/', std::path::MAIN_SEPARATOR_STR);
+    if let Ok(glob) = Pattern::new(&pat_os)
+        && glob.matches_path(path)
+    {
+        return true;
+    }
+    // Fallback for Windows: match against forward-slash string
+    let rel_forward = path.to_string_lossy().replace('\\', "/");
+    if let Ok(glob) = Pattern::new(pattern)
+        && glob.matches_path(Path::new(&rel_forward))
+    {
+        return true;
+    }
+    false
+}
+
+fn binary_file(path: &Path) -> Option<SkipReason> {
+    if let Some(ext) = path.extension() {
+        let ext_str = ext.to_string_lossy().to_lowercase();
+        let binary_extensions = [
+            "png", "jpg", "jpeg", "gif", "ico", "webp", "svg", "bmp", "tiff", "tif", "mp4", "avi",
+            "mov", "wmv", "flv", "webm", "mkv", "mp3", "wav", "ogg", "zip", "tar", "gz", "bz2",
+            "7z", "rar", "pdf", "doc", "docx", "xls", "xlsx", "exe", "dll", "so", "dylib", "woff",
+            "woff2", "ttf", "eot",
+        ];
+        if binary_extensions.contains(&ext_str.as_str()) {
+            return Some(SkipReason::BinaryExtension(ext_str));
+        }
+    }
+    // Byte check
+    if let Ok(mut file) = fs::File::open(path) {
+        let mut buffer = [0u8; 1024];
+        if let Ok(n) = file.read(&mut buffer)
+            && !looks_like_utf16(&buffer[..n])
+        {
+            for &byte in &buffer[..n] {
+                if byte == 0 || (byte < 32 && byte != 9 && byte != 10 && byte != 13) {
+                    return Some(SkipReason::BinaryContent);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The per-file selection rules, prepared once per run.
+pub(crate) struct Selector<'a> {
+    options: &'a FlattenOptions,
+    extensions: HashSet<String>,
+    allowed_filenames: HashSet<String>,
+    max_file_size: u64,
+    patterns: PatternFilter,
+    changed: Option<ChangedFiles>,
+}
+
+impl<'a> Selector<'a> {
+    pub(crate) fn new(options: &'a FlattenOptions) -> Result<Self> {
+        let extensions: HashSet<String> = options
+            .extensions
+            .iter()
+            .flatten()
+            .map(|e| if e.starts_with('.') { e.clone() } else { format!(".{}", e) })
+            .collect();
+        let allowed_filenames: HashSet<String> =
+            options.allowed_filenames.iter().flatten().cloned().collect();
+
+        if extensions.is_empty() && allowed_filenames.is_empty() && options.include_globs.is_none() {
+            return Err(anyhow::anyhow!(
+                "No allowed extensions, filenames, or include globs specified"
+            ));
+        }
+
+        Ok(Self {
+            options,
+            extensions,
+            allowed_filenames,
+            max_file_size: options.max_size_bytes(),
+            patterns: PatternFilter::new(options)?,
+            changed: ChangedFiles::load(options)?,
+        })
+    }
+
+    /// Decides whether a walked file goes into the output: `Ok` carries the rule that let
+    /// it in, `Err` the first rule that kept it out.
+    pub(crate) fn check(&self, path: &Path, base_dir: &Path) -> Result<String, SkipReason> {
+        if let Some(reason) = path_exclusion(path, self.options, base_dir) {
+            return Err(reason);
+        }
+        let change = match &self.changed {
+            Some(changed) => {
+                Some(changed.describe(path).ok_or_else(|| SkipReason::Unchanged(changed.label().to_string()))?)
+            }
+            None => None,
+        };
+        let mut allowed = allow_list_match(path, &self.extensions, &self.allowed_filenames, self.options)
+            .ok_or(SkipReason::NotAllowed)?;
+        if let Some(change) = change {
+            allowed = format!("{}, {}", allowed, change);
+        }
+        if let Some(reason) = binary_file(path) {
+            return Err(reason);
+        }
+        let relative_path = path.strip_prefix(base_dir).unwrap_or(path);
+        // Unreadable metadata is reported when the file is processed
+        if let Ok(metadata) = fs::metadata(path)
+            && metadata.len() > self.max_file_size
+        {
+            match truncation_for(self.options, relative_path) {
+                Some(truncation) => allowed = format!("{}, truncated to {} (over the size limit)", allowed, truncation),
+                None => {
+                    if self.options.verbose { info!("Skipping large file: {}", path.display()); }
+                    return Err(SkipReason::TooLarge { size: metadata.len(), limit: self.max_file_size });
+                }
+            }
+        }
+        match self.patterns.exclusion(path, relative_path) {
+            Some(reason) => Err(reason),
+            None => Ok(allowed),
+        }
+    }
+}
+
+pub(crate) fn is_safe_path(path: &Path, base_dir: &Path) -> bool {
+    if path.strip_prefix(base_dir).is_ok() { return true; }
+    let base_abs = base_dir.canonicalize().unwrap_or_else(|_| base_dir.to_path_buf());
+    if path.starts_with(&base_abs) { return true; }
+    if !path.is_absolute() {
+        let candidate = base_abs.join(path);
+        return candidate.starts_with(&base_abs);
+    }
+    false
+}
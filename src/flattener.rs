@@ -0,0 +1,890 @@
+use crate::budget::{budget_order, fit_token_budget, BudgetItem};
+use crate::comments::strip_comments;
+use crate::encoding::{decode, EncodingPolicy};
+use crate::explain::{explain, Explanation};
+use crate::filters::{build_walker, is_safe_path, Selector, SkipReason};
+use crate::git::{base_diff, find_git_root, get_git_changes, git_log, merge_base, repository_info, GitChanges};
+use crate::language::language_for_path;
+use crate::options::FlattenOptions;
+use crate::outline::{outline, selects};
+use crate::ordering::sort_by_order;
+use crate::profiles::ProfileManager;
+use crate::redact::{Redaction, Redactor, RuleMatches, SecretNumbers};
+use crate::render::{OutputHeader, OutputRenderer, OutputTotals};
+use crate::sink::{OutputSink, StringSink};
+use crate::template::TemplateRenderer;
+use crate::tokens::{count_tokens, Tokenizer};
+use crate::tree::DirectoryTree;
+use crate::truncate::truncation_for;
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Metadata collected for every flattened file.
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    /// Absolute (canonicalized) path of the file.
+    pub path: PathBuf,
+    /// Path relative to the target directory it was found in.
+    pub relative_path: PathBuf,
+    /// File extension without the leading dot (empty if none).
+    pub extension: String,
+    /// Language derived from the file name or extension, if known.
+    pub language: Option<&'static str>,
+    /// Size on disk in bytes.
+    pub size: u64,
+    /// Number of lines in the content.
+    pub line_count: usize,
+    /// Encoding the file was read as (`UTF-8`, `windows-1252`, `UTF-16LE`, ...); the
+    /// content is always transcoded to UTF-8.
+    pub encoding: &'static str,
+    /// The content is an outline with function bodies elided (see
+    /// [`FlattenOptions::outline`]).
+    pub outline: bool,
+    /// Lines cut from the middle of a file over the size limit (see
+    /// [`FlattenOptions::truncate`]); 0 if the content is complete.
+    pub truncated_lines: usize,
+    /// Secrets replaced by placeholders (see [`FlattenOptions::redact_secrets`]) and
+    /// matches of `[[redact]]` rules (see [`FlattenOptions::redact_rules`]).
+    pub redactions: Vec<Redaction>,
+    /// Token count of the file content alone.
+    pub token_count: usize,
+    /// Hex-encoded SHA-256 of the file content.
+    pub sha256: String,
+}
+
+/// A single file that made it into the flattened output.
+#[derive(Debug, Clone)]
+pub struct FlattenedFile {
+    pub metadata: FileMetadata,
+    pub content: String,
+}
+
+/// A file left out of the output because it did not fit the token budget
+/// (see [`FlattenOptions::max_tokens`]).
+#[derive(Debug, Clone)]
+pub struct OmittedFile {
+    /// Path relative to the target directory it was found in.
+    pub relative_path: PathBuf,
+    /// Tokens the file would have added to the output.
+    pub token_count: usize,
+}
+
+/// A file that passed the selection rules but was left out because of its size, because
+/// it is binary, or because it could not be read (see [`FlattenOptions::list_skipped`]).
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    /// Path relative to the target directory it was found in.
+    pub relative_path: PathBuf,
+    pub reason: SkipReason,
+    /// Size on disk in bytes, when it could be read.
+    pub size: Option<u64>,
+}
+
+impl SkippedFile {
+    fn new(path: &Path, base_dir: &Path, reason: SkipReason) -> Self {
+        Self {
+            relative_path: path.strip_prefix(base_dir).unwrap_or(path).to_path_buf(),
+            reason,
+            size: fs::metadata(path).ok().map(|m| m.len()),
+        }
+    }
+}
+
+/// Everything produced by an in-memory flattening run (see [`Flattener::run`]).
+#[derive(Debug)]
+pub struct FlattenResult {
+    /// The rendered output, ready to be written out.
+    pub content: String,
+    /// Files included in the output, in output order.
+    pub files: Vec<FlattenedFile>,
+    /// Git status and diffs, when git changes were requested and found.
+    pub git_changes: Option<GitChanges>,
+    /// Files dropped to stay under the token budget, by path.
+    pub omitted: Vec<OmittedFile>,
+    /// Files skipped for their size, as binary or as unreadable, by path.
+    pub skipped: Vec<SkippedFile>,
+    /// Matches of each `[[redact]]` rule, in rule order. Counted in dry runs too.
+    pub rule_matches: Vec<RuleMatches>,
+    pub file_count: usize,
+    /// Token count of the whole rendered output.
+    pub token_count: usize,
+    /// Tokenizer all token counts were made with.
+    pub tokenizer: Tokenizer,
+}
+
+/// What a streaming run (see [`Flattener::run_with_sink`]) reports once the output has
+/// been written. File contents are not retained.
+#[derive(Debug)]
+pub struct FlattenSummary {
+    /// Metadata of the files included in the output, in output order.
+    pub files: Vec<FileMetadata>,
+    /// Git status and diffs, when git changes were requested and found.
+    pub git_changes: Option<GitChanges>,
+    /// Files dropped to stay under the token budget, by path.
+    pub omitted: Vec<OmittedFile>,
+    /// Files skipped for their size, as binary or as unreadable, by path.
+    pub skipped: Vec<SkippedFile>,
+    /// Matches of each `[[redact]]` rule, in rule order. Counted in dry runs too.
+    pub rule_matches: Vec<RuleMatches>,
+    pub file_count: usize,
+    /// Token count of the whole rendered output.
+    pub token_count: usize,
+    /// Tokenizer all token counts were made with.
+    pub tokenizer: Tokenizer,
+}
+
+/// Entry point of the library: flattens the configured directories into a single document.
+///
+/// ```no_run
+/// use code_flattener::{FlattenOptions, Flattener};
+///
+/// let options = FlattenOptions::new().target_dir("src").extensions([".rs"]).markdown(true);
+/// let result = Flattener::new(options).run()?;
+/// println!("{} files, {} tokens", result.file_count, result.token_count);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Flattener {
+    options: FlattenOptions,
+    profile_manager: ProfileManager,
+    renderer: Option<Box<dyn OutputRenderer>>,
+}
+
+impl Flattener {
+    pub fn new(options: FlattenOptions) -> Self {
+        Self {
+            options,
+            profile_manager: ProfileManager::new(None),
+            renderer: None,
+        }
+    }
+
+    /// Uses the given manager (e.g. one holding custom profiles from a config file)
+    /// to resolve `FlattenOptions::profile`.
+    pub fn with_profile_manager(mut self, profile_manager: ProfileManager) -> Self {
+        self.profile_manager = profile_manager;
+        self
+    }
+
+    /// Renders output with a custom renderer instead of the one selected by
+    /// `FlattenOptions::format` / `FlattenOptions::template`.
+    pub fn with_renderer(mut self, renderer: Box<dyn OutputRenderer>) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// The options a run will use: the configured options with the profile applied.
+    pub fn resolved_options(&self) -> FlattenOptions {
+        let mut options = self.options.clone();
+        options.resolve_profile(&self.profile_manager);
+        options
+    }
+
+    pub fn options(&self) -> &FlattenOptions {
+        &self.options
+    }
+
+    /// Explains, for every file under the target directories, whether it would be
+    /// included and which rule decided it. Nothing is read beyond what the filters need.
+    pub fn explain(&self) -> Result<Vec<Explanation>> {
+        self.options.validate()?;
+        explain(&self.resolved_options())
+    }
+
+    /// Flattens everything into memory, keeping the content of every file.
+    pub fn run(&self) -> Result<FlattenResult> {
+        let mut sink = StringSink::new();
+        let mut files = Vec::new();
+        let summary = self.flatten(&mut sink, |file| files.push(file))?;
+        Ok(FlattenResult {
+            content: sink.into_string(),
+            files,
+            git_changes: summary.git_changes,
+            omitted: summary.omitted,
+            skipped: summary.skipped,
+            rule_matches: summary.rule_matches,
+            file_count: summary.file_count,
+            token_count: summary.token_count,
+            tokenizer: summary.tokenizer,
+        })
+    }
+
+    /// Streams the output into `sink` file by file, in output order, so memory use stays
+    /// bounded by a small window of files rather than by the size of the tree.
+    pub fn run_with_sink(&self, sink: &mut dyn OutputSink) -> Result<FlattenSummary> {
+        self.flatten(sink, |_| {})
+    }
+
+    fn flatten(
+        &self,
+        sink: &mut dyn OutputSink,
+        keep: impl FnMut(FlattenedFile),
+    ) -> Result<FlattenSummary> {
+        self.options.validate()?;
+        let options = self.resolved_options();
+
+        let selected: Box<dyn OutputRenderer>;
+        let renderer: &dyn OutputRenderer = match (&self.renderer, &options.template) {
+            (Some(renderer), _) => renderer.as_ref(),
+            (None, Some(template)) => {
+                selected = Box::new(TemplateRenderer::from_file(template)?);
+                selected.as_ref()
+            }
+            (None, None) => {
+                selected = options.format.renderer();
+                selected.as_ref()
+            }
+        };
+        process_directories(&options, renderer, sink, keep)
+    }
+}
+
+/// A walked file together with the target directory it was found in.
+struct Candidate {
+    path: PathBuf,
+    base_dir: usize,
+}
+
+enum FileOutcome {
+    /// Could not be read (parallel mode or `list_skipped` only; other runs fail instead).
+    Skipped(SkipReason),
+    /// Selected in dry-run mode. The file was only read to count `[[redact]]` rule
+    /// matches, if there are any rules.
+    WouldProcess(Vec<Redaction>),
+    Processed(FlattenedFile),
+}
+
+/// Number of files processed concurrently per batch in parallel mode. Results of a batch
+/// are written out in order before the next batch starts, which bounds memory use.
+fn parallel_batch_size() -> usize {
+    rayon::current_num_threads().max(1) * 8
+}
+
+fn write_chunk(
+    sink: &mut dyn OutputSink,
+    chunk: &str,
+    token_count: &mut usize,
+    options: &FlattenOptions,
+) -> Result<()> {
+    if !chunk.is_empty() {
+        sink.write_chunk(chunk)?;
+        *token_count += count_tokens(chunk, options.resolved_tokenizer());
+    }
+    Ok(())
+}
+
+fn process_directories(
+    options: &FlattenOptions,
+    renderer: &dyn OutputRenderer,
+    sink: &mut dyn OutputSink,
+    mut keep: impl FnMut(FlattenedFile),
+) -> Result<FlattenSummary> {
+    info!(
+        "Settings - extensions: {:?}, filenames: {:?}, include_globs: {:?}, max_size: {}MB",
+        options.extensions, options.allowed_filenames, options.include_globs, options.max_size
+    );
+
+    let selector = Selector::new(options)?;
+    let redactor = Redactor::new(options)?;
+
+    // Resolved before anything is written, so an unknown ref fails the run up front
+    let diff_base = match &options.git_diff_base {
+        Some(base) if !options.dry_run => {
+            let start = options.target_dirs.first().cloned().unwrap_or_else(|| PathBuf::from("."));
+            let root = find_git_root(&start)?.with_context(|| {
+                format!("Cannot diff against '{}': {} is not in a git repository", base, start.display())
+            })?;
+            let merge_base = merge_base(&root, base).with_context(|| format!("Cannot diff against '{}'", base))?;
+            Some((base.clone(), merge_base))
+        }
+        _ => None,
+    };
+
+    info!("Starting processing...");
+
+    let mut base_dirs = Vec::new();
+    let mut candidates = Vec::new();
+    for start_dir in &options.target_dirs {
+        let start_dir = fs::canonicalize(start_dir)
+            .with_context(|| format!("Failed to canonicalize path: {}", start_dir.display()))?;
+
+        if !is_safe_path(&start_dir, &start_dir) {
+             return Err(anyhow::anyhow!("Path traversal detected: {}", start_dir.display()));
+        }
+
+        let walker = build_walker(&start_dir, options);
+        candidates.extend(
+            walker
+                .build()
+                .filter_map(Result::ok)
+                .filter(|entry| !entry.file_type().is_some_and(|t| t.is_dir()))
+                .map(|entry| Candidate {
+                    path: entry.into_path(),
+                    base_dir: base_dirs.len(),
+                }),
+        );
+        base_dirs.push(start_dir);
+    }
+
+    // Decide which files make it into the output before writing anything, so the
+    // preamble can describe the final selection.
+    let check = |candidate: &Candidate| -> Option<SkipReason> {
+        selector.check(&candidate.path, &base_dirs[candidate.base_dir]).err()
+    };
+    let reasons: Vec<Option<SkipReason>> = if options.parallel {
+        candidates.par_iter().map(check).collect()
+    } else {
+        candidates.iter().map(check).collect()
+    };
+    let selection: Vec<bool> = reasons.iter().map(Option::is_none).collect();
+    let mut skipped: Vec<SkippedFile> = candidates
+        .iter()
+        .zip(reasons)
+        .filter_map(|(candidate, reason)| {
+            let reason = reason.filter(SkipReason::is_listed_as_skipped)?;
+            Some(SkippedFile::new(&candidate.path, &base_dirs[candidate.base_dir], reason))
+        })
+        .collect();
+
+    let tree = (options.tree || options.tree_excluded)
+        .then(|| render_tree(&base_dirs, &candidates, &selection, options.tree_excluded));
+
+    let mut selected: Vec<Candidate> = candidates
+        .into_iter()
+        .zip(selection)
+        .filter_map(|(candidate, selected)| selected.then_some(candidate))
+        .collect();
+    sort_by_order(&mut selected, options.sort, |c| c.path.as_path());
+
+    // Collected before anything is written, so secrets in diffs and the log can fail the run
+    // (in dry runs too)
+    let (mut git_changes, git_redactions) = if options.dry_run && !options.fail_on_secrets {
+        (None, Vec::new())
+    } else {
+        // Files the walk selected, and deleted files the path filters would accept
+        let selected_paths: HashSet<&Path> = selected.iter().map(|c| c.path.as_path()).collect();
+        let keep = |path: &Path| {
+            selected_paths.contains(path)
+                || (!path.exists()
+                    && base_dirs.iter().any(|dir| path.starts_with(dir) && selector.check(path, dir).is_ok()))
+        };
+        collect_git_changes(options, &redactor, diff_base.as_ref(), keep)
+    };
+
+    let handle = |candidate: &Candidate| -> Result<FileOutcome> {
+        let base_dir = &base_dirs[candidate.base_dir];
+        if options.dry_run {
+            let redactions = if redactor.has_rules() || options.fail_on_secrets {
+                dry_run_redactions(&candidate.path, base_dir, options, &redactor)?
+            } else {
+                Vec::new()
+            };
+            return Ok(FileOutcome::WouldProcess(redactions));
+        }
+        process_single_file(&candidate.path, base_dir, options, &redactor)
+    };
+
+    let mut summary = FlattenSummary {
+        files: Vec::new(),
+        git_changes: None,
+        omitted: Vec::new(),
+        skipped: Vec::new(),
+        rule_matches: RuleMatches::for_rules(&options.redact_rules),
+        file_count: 0,
+        token_count: 0,
+        tokenizer: options.resolved_tokenizer(),
+    };
+
+    // In parallel mode (and when skipped files are listed) a file that cannot be read is
+    // skipped rather than failing the run
+    let handle_or_skip = |candidate: &Candidate| -> FileOutcome {
+        handle(candidate).unwrap_or_else(|e| {
+            warn!("Failed to process {}: {}", candidate.path.display(), e);
+            FileOutcome::Skipped(read_failure(&e))
+        })
+    };
+    let handle_sequential = |candidate: &Candidate| -> Result<FileOutcome> {
+        if options.list_skipped {
+            Ok(handle_or_skip(candidate))
+        } else {
+            handle(candidate)
+        }
+    };
+
+    // Every file must be read before the first one is written when the output has a token
+    // budget, and before anything is written when secrets fail the run
+    let buffered = (options.fail_on_secrets || (options.max_tokens.is_some() && !options.dry_run))
+        .then(|| -> Result<Vec<FileOutcome>> {
+            Ok(if options.parallel {
+                selected.par_iter().map(handle_or_skip).collect()
+            } else {
+                selected.iter().map(handle_sequential).collect::<Result<_>>()?
+            })
+        })
+        .transpose()?;
+    if options.fail_on_secrets
+        && let Some(outcomes) = &buffered
+    {
+        check_secrets(&selected, &base_dirs, outcomes, &git_redactions)?;
+    }
+
+    if !options.dry_run {
+        let repository = if renderer.wants_repository_info() {
+            base_dirs
+                .first()
+                .and_then(|dir| find_git_root(dir).ok().flatten())
+                .map(|root| repository_info(&root))
+        } else {
+            None
+        };
+        let header = OutputHeader {
+            roots: &base_dirs,
+            profile: options.profile.as_deref(),
+            repository: repository.as_ref(),
+            tree: tree.as_deref(),
+        };
+        write_chunk(sink, &renderer.preamble(&header), &mut summary.token_count, options)?;
+    }
+
+    // The git section and the epilogue are written whichever files fit, so the budget for
+    // files is what they leave
+    let dropped = match (&buffered, options.max_tokens) {
+        (Some(outcomes), Some(max_tokens)) if !options.dry_run => {
+            // Files that cannot be read are listed in the epilogue along with the walk's
+            let mut listed = Vec::new();
+            if options.list_skipped {
+                listed = skipped.clone();
+                listed.extend(selected.iter().zip(outcomes).filter_map(|(candidate, outcome)| match outcome {
+                    FileOutcome::Skipped(reason) => {
+                        Some(SkippedFile::new(&candidate.path, &base_dirs[candidate.base_dir], reason.clone()))
+                    }
+                    _ => None,
+                }));
+                listed.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+            }
+            let git_tokens = count_tokens(&renderer.git_section(git_changes.as_ref()), options.resolved_tokenizer());
+            let fit = |git| fit_outcomes(outcomes, summary.token_count, git, max_tokens, &listed, renderer, options);
+            let (mut dropped, mut omitted, mut total) = fit(git_changes.as_ref());
+            if total > max_tokens && git_tokens > 0 {
+                warn!(
+                    "The git section ({} tokens) does not fit in the token budget of {}; leaving it out",
+                    git_tokens, max_tokens
+                );
+                (dropped, omitted, total) = fit(None);
+                git_changes = None;
+            }
+            if total > max_tokens {
+                warn!("Token budget of {} is too small for the preamble and epilogue; writing {} tokens", max_tokens, total);
+            }
+            if !omitted.is_empty() {
+                info!("Token budget of {} reached; omitted {} files", max_tokens, omitted.len());
+            }
+            summary.omitted = omitted;
+            dropped
+        }
+        _ => Vec::new(),
+    };
+
+    let mut numbers = SecretNumbers::default();
+    let mut emit = |candidate: &Candidate, outcome: FileOutcome| -> Result<()> {
+        match outcome {
+            FileOutcome::Skipped(reason) => {
+                skipped.push(SkippedFile::new(&candidate.path, &base_dirs[candidate.base_dir], reason));
+            }
+            FileOutcome::WouldProcess(redactions) => {
+                info!("DRY-RUN: would process {}", candidate.path.display());
+                RuleMatches::count(&mut summary.rule_matches, &redactions);
+                summary.file_count += 1;
+            }
+            FileOutcome::Processed(mut file) => {
+                number_secrets(&mut file, &mut numbers, options.resolved_tokenizer());
+                let chunk = renderer.file(&file, summary.file_count);
+                sink.write_file(&chunk, &file.metadata)?;
+                summary.token_count += count_tokens(&chunk, options.resolved_tokenizer());
+                summary.file_count += 1;
+                RuleMatches::count(&mut summary.rule_matches, &file.metadata.redactions);
+                summary.files.push(file.metadata.clone());
+                keep(file);
+            }
+        }
+        Ok(())
+    };
+
+    if let Some(outcomes) = buffered {
+        for (i, (candidate, outcome)) in selected.iter().zip(outcomes).enumerate() {
+            if !dropped.get(i).copied().unwrap_or(false) {
+                emit(candidate, outcome)?;
+            }
+        }
+    } else if options.parallel {
+        for batch in selected.chunks(parallel_batch_size()) {
+            let outcomes: Vec<_> = batch.par_iter().map(handle_or_skip).collect();
+            for (candidate, outcome) in batch.iter().zip(outcomes) {
+                emit(candidate, outcome)?;
+            }
+        }
+    } else {
+        for candidate in &selected {
+            emit(candidate, handle_sequential(candidate)?)?;
+        }
+    }
+
+    skipped.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    summary.skipped = skipped;
+
+    if !options.dry_run {
+        if let Some(changes) = &mut git_changes {
+            number_git_secrets(changes, &mut numbers);
+        }
+        summary.git_changes = git_changes;
+        let git_section = renderer.git_section(summary.git_changes.as_ref());
+        write_chunk(sink, &git_section, &mut summary.token_count, options)?;
+
+        let totals = OutputTotals {
+            file_count: summary.file_count,
+            token_count: summary.token_count,
+            tokenizer: options.resolved_tokenizer(),
+            omitted: &summary.omitted,
+            skipped: if options.list_skipped { &summary.skipped } else { &[] },
+        };
+        write_chunk(sink, &renderer.epilogue(&totals), &mut summary.token_count, options)?;
+    }
+
+    sink.finish()?;
+    Ok(summary)
+}
+
+/// Numbers the secret placeholders in `file` (see [`SecretNumbers`]), updating the token
+/// count and hash of its content.
+fn number_secrets(file: &mut FlattenedFile, numbers: &mut SecretNumbers, tokenizer: Tokenizer) {
+    if SecretNumbers::applies_to(&file.content) {
+        file.content = numbers.apply(&file.content);
+        file.metadata.token_count = count_tokens(&file.content, tokenizer);
+        file.metadata.sha256 = format!("{:x}", Sha256::digest(file.content.as_bytes()));
+    }
+}
+
+/// Numbers the secret placeholders in the git section, in the order it is rendered.
+fn number_git_secrets(changes: &mut GitChanges, numbers: &mut SecretNumbers) {
+    let texts = [&mut changes.staged_diff, &mut changes.unstaged_diff, &mut changes.base_diff, &mut changes.log];
+    for text in texts.into_iter().flatten() {
+        *text = numbers.apply(text);
+    }
+}
+
+/// Tokens of each of `files` rendered as the first files of the output, then of the `git`
+/// section after them, with secrets numbered as they would be when written.
+fn rendered_tokens(
+    files: &[&FlattenedFile],
+    git: Option<&GitChanges>,
+    renderer: &dyn OutputRenderer,
+    tokenizer: Tokenizer,
+) -> Vec<usize> {
+    let mut numbers = SecretNumbers::default();
+    let mut tokens: Vec<usize> = files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let chunk = if SecretNumbers::applies_to(&file.content) {
+                let mut file = (*file).clone();
+                number_secrets(&mut file, &mut numbers, tokenizer);
+                renderer.file(&file, index)
+            } else {
+                renderer.file(file, index)
+            };
+            count_tokens(&chunk, tokenizer)
+        })
+        .collect();
+    if let Some(git) = git {
+        let mut git = git.clone();
+        number_git_secrets(&mut git, &mut numbers);
+        tokens.push(count_tokens(&renderer.git_section(Some(&git)), tokenizer));
+    }
+    tokens
+}
+
+/// Picks the buffered files to leave out so that the output fits in `max_tokens`, given the
+/// `written` tokens of the preamble, the `git` section and the epilogue that lists the
+/// files left out (and `skipped`). Returns one flag per outcome, the omitted files and the
+/// tokens of the whole output, over `max_tokens` only if nothing else can be left out.
+fn fit_outcomes(
+    outcomes: &[FileOutcome],
+    written: usize,
+    git: Option<&GitChanges>,
+    max_tokens: usize,
+    skipped: &[SkippedFile],
+    renderer: &dyn OutputRenderer,
+    options: &FlattenOptions,
+) -> (Vec<bool>, Vec<OmittedFile>, usize) {
+    let tokenizer = options.resolved_tokenizer();
+    let processed: Vec<(usize, &FlattenedFile)> = outcomes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, outcome)| match outcome {
+            FileOutcome::Processed(file) => Some((i, file)),
+            _ => None,
+        })
+        .collect();
+    // As written when none is left out; the index only changes separators and numbering
+    let files: Vec<&FlattenedFile> = processed.iter().map(|(_, file)| *file).collect();
+    let costs = rendered_tokens(&files, None, renderer, tokenizer);
+    let items: Vec<BudgetItem> = processed
+        .iter()
+        .zip(&costs)
+        .map(|((_, file), &cost)| BudgetItem {
+            path: &file.metadata.path,
+            relative_path: &file.metadata.relative_path,
+            size: file.metadata.size,
+            cost,
+        })
+        .collect();
+    let order = budget_order(&items, options);
+
+    // Shrinks the budget by the overshoot until the files as written, at their real
+    // indexes, the git section and the epilogue fit
+    let git_tokens = rendered_tokens(&[], git, renderer, tokenizer).iter().sum::<usize>();
+    let mut budget = max_tokens.saturating_sub(written + git_tokens);
+    loop {
+        let fits = fit_token_budget(&items, &order, budget);
+        let mut omitted: Vec<OmittedFile> = processed
+            .iter()
+            .zip(&costs)
+            .zip(&fits)
+            .filter(|(_, fits)| !**fits)
+            .map(|(((_, file), &cost), _)| OmittedFile {
+                relative_path: file.metadata.relative_path.clone(),
+                token_count: cost,
+            })
+            .collect();
+        omitted.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let kept: Vec<&FlattenedFile> =
+            processed.iter().zip(&fits).filter(|(_, fits)| **fits).map(|((_, file), _)| *file).collect();
+        let token_count = written + rendered_tokens(&kept, git, renderer, tokenizer).iter().sum::<usize>();
+        let totals = OutputTotals { file_count: kept.len(), token_count, tokenizer, omitted: &omitted, skipped };
+        let total = token_count + count_tokens(&renderer.epilogue(&totals), tokenizer);
+        if total <= max_tokens || budget == 0 {
+            let mut dropped = vec![false; outcomes.len()];
+            for ((i, _), fits) in processed.iter().zip(fits) {
+                dropped[*i] = !fits;
+            }
+            return (dropped, omitted, total);
+        }
+        budget = budget.saturating_sub(total - max_tokens);
+    }
+}
+
+/// Collects the git section: status and working-tree diffs with `include_git_changes`,
+/// the commit log with `git_log` and the diff against `diff_base` (a ref and its merge
+/// base), limited to the files `keep` accepts. Returns it redacted, with the redactions
+/// made, labelled by the part of the section they were found in.
+fn collect_git_changes(
+    options: &FlattenOptions,
+    redactor: &Redactor,
+    diff_base: Option<&(String, String)>,
+    keep: impl Fn(&Path) -> bool,
+) -> (Option<GitChanges>, Vec<(String, Redaction)>) {
+    if !options.include_git_changes && options.git_log.is_none() && diff_base.is_none() {
+        return (None, Vec::new());
+    }
+    let Ok(Some(root)) = find_git_root(options.target_dirs.first().unwrap_or(&PathBuf::from("."))) else {
+        return (None, Vec::new());
+    };
+    // The commit history and base diff can be had without status and working-tree diffs
+    let changes = if options.include_git_changes {
+        get_git_changes(&root, !options.no_staged_diff, !options.no_unstaged_diff, options.verbose).ok().flatten()
+    } else {
+        Some(GitChanges { repo_root: root.clone(), ..GitChanges::default() })
+    };
+    let Some(mut changes) = changes else { return (None, Vec::new()) };
+    if let Some(count) = options.git_log {
+        changes.log = git_log(&root, count, options.git_log_stat);
+    }
+    if let Some((base, commit)) = diff_base {
+        changes.diff_base = Some(base.clone());
+        changes.base_diff = base_diff(&root, commit, keep).unwrap_or_else(|e| {
+            warn!("Failed to diff against {}: {}", base, e);
+            None
+        });
+    }
+    if !redactor.is_active() {
+        return (Some(changes), Vec::new());
+    }
+    let redactions = redact_diffs(&mut changes, redactor);
+    (Some(changes), redactions)
+}
+
+/// Redacts git diffs and the commit log like file contents (see [`Redactor::redact_diff`]),
+/// returning the redactions made with the part of the git section they were in.
+fn redact_diffs(changes: &mut GitChanges, redactor: &Redactor) -> Vec<(String, Redaction)> {
+    let base_diff = format!("git diff {}...HEAD", changes.diff_base.as_deref().unwrap_or_default());
+    let texts = [
+        ("git diff --staged", &mut changes.staged_diff),
+        ("git diff", &mut changes.unstaged_diff),
+        (base_diff.as_str(), &mut changes.base_diff),
+        ("git log", &mut changes.log),
+    ];
+    let mut found = Vec::new();
+    for (label, text) in texts {
+        let Some(text) = text else { continue };
+        let (redacted, redactions) = redactor.redact_diff(text);
+        if !redactions.is_empty() {
+            info!("Redacted {} matches in {}", redactions.len(), label);
+            *text = redacted;
+            found.extend(redactions.into_iter().map(|r| (label.to_string(), r)));
+        }
+    }
+    found
+}
+
+/// Fails with a list of the secrets found in files and in the git section when
+/// `fail_on_secrets` is set.
+fn check_secrets(
+    selected: &[Candidate],
+    base_dirs: &[PathBuf],
+    outcomes: &[FileOutcome],
+    git_redactions: &[(String, Redaction)],
+) -> Result<()> {
+    let in_files = selected
+        .iter()
+        .zip(outcomes)
+        .filter_map(|(candidate, outcome)| match outcome {
+            FileOutcome::Processed(file) => Some((candidate, &file.metadata.redactions)),
+            FileOutcome::WouldProcess(redactions) => Some((candidate, redactions)),
+            FileOutcome::Skipped(_) => None,
+        })
+        .flat_map(|(candidate, redactions)| {
+            let relative_path = candidate.path.strip_prefix(&base_dirs[candidate.base_dir]).unwrap_or(&candidate.path);
+            let path = relative_path.to_string_lossy().replace('\\', "/");
+            redactions.iter().map(move |r| (path.clone(), r))
+        });
+    let in_git = git_redactions.iter().map(|(label, r)| (label.clone(), r));
+    let found: Vec<String> = in_files
+        .chain(in_git)
+        .filter(|(_, r)| r.secret)
+        .map(|(location, r)| format!("  {}:{}: {}", location, r.line, r.kind))
+        .collect();
+    if !found.is_empty() {
+        anyhow::bail!("Found {} secrets; nothing was written:\n{}", found.len(), found.join("\n"));
+    }
+    Ok(())
+}
+
+/// Renders one tree per target directory from the walked files.
+fn render_tree(
+    base_dirs: &[PathBuf],
+    candidates: &[Candidate],
+    selection: &[bool],
+    show_excluded: bool,
+) -> String {
+    let mut trees: Vec<DirectoryTree> = base_dirs.iter().map(|_| DirectoryTree::new()).collect();
+    for (candidate, &selected) in candidates.iter().zip(selection) {
+        if selected || show_excluded {
+            let base_dir = &base_dirs[candidate.base_dir];
+            let relative = candidate.path.strip_prefix(base_dir).unwrap_or(&candidate.path);
+            trees[candidate.base_dir].add(relative, !selected);
+        }
+    }
+    base_dirs
+        .iter()
+        .zip(&trees)
+        .map(|(dir, tree)| {
+            let label = dir.file_name().map_or_else(|| dir.to_string_lossy(), |n| n.to_string_lossy());
+            tree.render(&label)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Maps a read error to a skip reason, telling invalid UTF-8 apart from I/O failures.
+fn read_failure(error: &anyhow::Error) -> SkipReason {
+    match error.root_cause().downcast_ref::<std::io::Error>() {
+        Some(io) if io.kind() == std::io::ErrorKind::InvalidData => SkipReason::NotUtf8,
+        Some(io) => SkipReason::Unreadable(io.to_string()),
+        None => SkipReason::Unreadable(error.root_cause().to_string()),
+    }
+}
+
+/// Reads and redacts a file in dry-run mode, only to count rule matches.
+fn dry_run_redactions(
+    path: &Path,
+    base_dir: &Path,
+    options: &FlattenOptions,
+    redactor: &Redactor,
+) -> Result<Vec<Redaction>> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read file {}", path.display()))?;
+    let Some((content, _)) = decode(&bytes, options.encoding_fallback) else {
+        return Ok(Vec::new());
+    };
+    let relative_path = path.strip_prefix(base_dir).unwrap_or(path);
+    Ok(redactor.redact(path, relative_path, &content).1)
+}
+
+fn process_single_file(
+    path: &Path,
+    base_dir: &Path,
+    options: &FlattenOptions,
+    redactor: &Redactor,
+) -> Result<FileOutcome> {
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to get metadata for {}", path.display()))?;
+
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read file {}", path.display()))?;
+    let Some((content, encoding)) = decode(&bytes, options.encoding_fallback) else {
+        if options.encoding_fallback == EncodingPolicy::Skip {
+            if options.verbose { info!("Skipping non-UTF-8 file: {}", path.display()); }
+            return Ok(FileOutcome::Skipped(SkipReason::NotUtf8));
+        }
+        let error = std::io::Error::new(std::io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+        return Err(anyhow::Error::new(error).context(format!("Failed to read file {}", path.display())));
+    };
+
+    let relative_path = path.strip_prefix(base_dir).unwrap_or(path).to_path_buf();
+    // Before anything else touches the content, so lines refer to the file on disk
+    let (content, redactions) = if redactor.is_active() {
+        redactor.redact(path, &relative_path, &content)
+    } else {
+        (content, Vec::new())
+    };
+
+    let content = if options.strip_comments || options.strip_docstrings {
+        strip_comments(path, &content, options.strip_docstrings)
+    } else {
+        content
+    };
+    let outlined = selects(options, &relative_path);
+    let content = if outlined { outline(path, &content) } else { content };
+    let content = options.normalize.unwrap_or_default().apply(content);
+    let (content, truncated_lines) = match truncation_for(options, &relative_path) {
+        Some(truncation) if metadata.len() > options.max_size_bytes() => {
+            truncation.apply(content, options.resolved_tokenizer())
+        }
+        _ => (content, 0),
+    };
+
+    if options.verbose { info!("Processed: {}", path.display()); }
+
+    Ok(FileOutcome::Processed(FlattenedFile {
+        metadata: FileMetadata {
+            path: path.to_path_buf(),
+            relative_path,
+            extension: path.extension().unwrap_or_default().to_string_lossy().into_owned(),
+            language: language_for_path(path),
+            size: metadata.len(),
+            line_count: content.lines().count(),
+            encoding,
+            outline: outlined,
+            truncated_lines,
+            redactions,
+            token_count: count_tokens(&content, options.resolved_tokenizer()),
+            sha256: format!("{:x}", Sha256::digest(content.as_bytes())),
+        },
+        content,
+    }))
+}
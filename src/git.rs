@@ -0,0 +1,249 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::warn;
+
+pub(crate) fn find_git_root(start_path: &Path) -> Result<Option<PathBuf>> {
+    let mut current_path = fs::canonicalize(start_path)?;
+    loop {
+        if current_path.join(".git").is_dir() {
+            return Ok(Some(current_path));
+        }
+        if !current_path.pop() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Git status and working-tree diffs collected for the git section of the output.
+#[derive(Debug, Clone, Default)]
+pub struct GitChanges {
+    pub repo_root: PathBuf,
+    /// `git status --porcelain` output, if not empty.
+    pub status: Option<String>,
+    /// `git diff --staged` output, if requested and not empty.
+    pub staged_diff: Option<String>,
+    /// `git diff` output, if requested and not empty.
+    pub unstaged_diff: Option<String>,
+    /// The ref `base_diff` was taken against, e.g. `main`.
+    pub diff_base: Option<String>,
+    /// `git diff <diff_base>...HEAD` limited to the selected files, if requested and not
+    /// empty.
+    pub base_diff: Option<String>,
+    /// The most recent commits (see [`git_log`]), if requested and any exist.
+    pub log: Option<String>,
+}
+
+fn non_empty(stdout: &[u8]) -> Option<String> {
+    let s = String::from_utf8_lossy(stdout);
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+pub(crate) fn get_git_changes(
+    repo_path: &Path,
+    include_staged: bool,
+    include_unstaged: bool,
+    verbose: bool,
+) -> Result<Option<GitChanges>> {
+    let mut changes = GitChanges {
+        repo_root: repo_path.to_path_buf(),
+        ..GitChanges::default()
+    };
+
+    let status_out = Command::new("git")
+        .args(["status", "--porcelain", "-uall"])
+        .current_dir(repo_path)
+        .output()?;
+
+    if status_out.status.success() {
+        changes.status = non_empty(&status_out.stdout);
+    } else if verbose {
+        warn!("git status failed");
+    }
+
+    if include_staged {
+        let diff = Command::new("git")
+            .args(["diff", "--staged"])
+            .current_dir(repo_path)
+            .output()?;
+        if diff.status.success() {
+            changes.staged_diff = non_empty(&diff.stdout);
+        }
+    }
+
+    if include_unstaged {
+        let diff = Command::new("git")
+            .args(["diff"])
+            .current_dir(repo_path)
+            .output()?;
+        if diff.status.success() {
+            changes.unstaged_diff = non_empty(&diff.stdout);
+        }
+    }
+
+    Ok(Some(changes))
+}
+
+/// The last `count` commits, one `<hash> <date> <author>: <subject>` line each, followed by
+/// the files each one changed when `stat` is set.
+pub(crate) fn git_log(repo_path: &Path, count: usize, stat: bool) -> Option<String> {
+    let count = count.to_string();
+    let mut args = vec!["log", "-n", &count, "--date=short", "--format=%h %ad %an: %s"];
+    if stat {
+        args.push("--stat");
+    }
+    git_stdout(repo_path, &args)
+}
+
+/// The commit HEAD forked from `base` at, so a diff against it shows only the changes
+/// made on HEAD.
+pub(crate) fn merge_base(repo_path: &Path, base: &str) -> Result<String> {
+    let out = Command::new("git")
+        .args(["merge-base", base, "HEAD"])
+        .current_dir(repo_path)
+        .output()?;
+    if !out.status.success() {
+        anyhow::bail!("Cannot resolve git ref '{}': {}", base, String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// `git diff <merge_base> HEAD` limited to the changed files `keep` accepts, given as
+/// absolute paths under `repo_path`. A renamed file is kept, with its old path, when its
+/// new path is.
+pub(crate) fn base_diff(repo_path: &Path, merge_base: &str, keep: impl Fn(&Path) -> bool) -> Result<Option<String>> {
+    let names = Command::new("git")
+        .args(["diff", "--name-status", "-z", "-M", merge_base, "HEAD"])
+        .current_dir(repo_path)
+        .output()?;
+    if !names.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&names.stderr).trim());
+    }
+
+    // `<status>\0<path>\0`, or `<status>\0<old path>\0<new path>\0` for renames and copies
+    let names = String::from_utf8_lossy(&names.stdout);
+    let mut fields = names.split('\0').filter(|field| !field.is_empty());
+    let mut pathspecs = Vec::new();
+    while let Some(status) = fields.next() {
+        let count = if status.starts_with(['R', 'C']) { 2 } else { 1 };
+        let paths: Vec<&str> = fields.by_ref().take(count).collect();
+        if paths.last().is_some_and(|path| keep(&repo_path.join(path))) {
+            pathspecs.extend(paths.iter().map(|path| format!(":(literal){}", path)));
+        }
+    }
+    if pathspecs.is_empty() {
+        return Ok(None);
+    }
+
+    let diff = Command::new("git")
+        .args(["diff", "-M", merge_base, "HEAD", "--"])
+        .args(&pathspecs)
+        .current_dir(repo_path)
+        .output()?;
+    if !diff.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&diff.stderr).trim());
+    }
+    Ok(non_empty(&diff.stdout))
+}
+
+/// Files added or modified between `base` and the working tree, untracked files included,
+/// as absolute paths under `repo_path`. Deleted files are left out.
+pub(crate) fn changed_files(repo_path: &Path, base: &str) -> Result<HashSet<PathBuf>> {
+    let mut files = HashSet::new();
+    for args in [
+        &["diff", "--name-only", "-z", "--no-renames", "--diff-filter=d", base][..],
+        &["ls-files", "--others", "--exclude-standard", "-z"][..],
+    ] {
+        let out = Command::new("git").args(args).current_dir(repo_path).output()?;
+        if !out.status.success() {
+            anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim());
+        }
+        let names = String::from_utf8_lossy(&out.stdout);
+        files.extend(names.split('\0').filter(|name| !name.is_empty()).map(|name| repo_path.join(name)));
+    }
+    Ok(files)
+}
+
+/// Branch and commit of the repository the output was taken from.
+#[derive(Debug, Clone)]
+pub struct RepositoryInfo {
+    pub root: PathBuf,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+}
+
+fn git_stdout(repo_path: &Path, args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).current_dir(repo_path).output().ok()?;
+    if out.status.success() { non_empty(&out.stdout) } else { None }
+}
+
+pub(crate) fn repository_info(repo_path: &Path) -> RepositoryInfo {
+    RepositoryInfo {
+        root: repo_path.to_path_buf(),
+        branch: git_stdout(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]),
+        commit: git_stdout(repo_path, &["rev-parse", "HEAD"]),
+    }
+}
+
+/// Counts how many commits touched each file, keyed by absolute path under `repo_path`.
+pub(crate) fn file_churn(repo_path: &Path) -> Result<HashMap<PathBuf, usize>> {
+    let log = Command::new("git")
+        .args(["log", "--format=", "--name-only", "-z"])
+        .current_dir(repo_path)
+        .output()?;
+    if !log.status.success() {
+        anyhow::bail!("git log failed: {}", String::from_utf8_lossy(&log.stderr).trim());
+    }
+
+    // NUL-separated names are neither quoted nor escaped; commits are separated by empty ones
+    let mut churn = HashMap::new();
+    for name in String::from_utf8_lossy(&log.stdout).split('\0').filter(|name| !name.is_empty()) {
+        *churn.entry(repo_path.join(name)).or_insert(0) += 1;
+    }
+    Ok(churn)
+}
+
+/// Ranks recently changed files by recency, keyed by absolute path under `repo_path`:
+/// 0 for uncommitted changes (staged, unstaged or untracked), `n` for files last touched
+/// by the `n`-th most recent of the last `commits` commits. Other files are absent.
+pub(crate) fn recent_changes(repo_path: &Path, commits: usize) -> Result<HashMap<PathBuf, usize>> {
+    let mut ranks = HashMap::new();
+
+    // NUL-separated names are neither quoted nor escaped (and not trimmed like `git_stdout`'s)
+    for args in [
+        &["diff", "--name-only", "-z", "HEAD"][..],
+        &["ls-files", "--others", "--exclude-standard", "-z"][..],
+    ] {
+        let Ok(out) = Command::new("git").args(args).current_dir(repo_path).output() else { continue };
+        if out.status.success() {
+            for name in String::from_utf8_lossy(&out.stdout).split('\0').filter(|name| !name.is_empty()) {
+                ranks.insert(repo_path.join(name), 0);
+            }
+        }
+    }
+
+    let log = Command::new("git")
+        .args(["log", "-n", &commits.to_string(), "--format=%x01", "--name-only", "-z"])
+        .current_dir(repo_path)
+        .output()?;
+    if !log.status.success() {
+        anyhow::bail!("git log failed: {}", String::from_utf8_lossy(&log.stderr).trim());
+    }
+
+    // Each commit is a \x01 entry; a newline separates it from the first of its names
+    let mut rank = 0;
+    for entry in String::from_utf8_lossy(&log.stdout).split('\0') {
+        if entry == "\x01" {
+            rank += 1;
+            continue;
+        }
+        let name = entry.strip_prefix('\n').unwrap_or(entry);
+        if !name.is_empty() {
+            ranks.entry(repo_path.join(name)).or_insert(rank);
+        }
+    }
+    Ok(ranks)
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+/// Best-effort language name for a file, derived from its name or extension.
+pub fn language_for_path(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_string_lossy();
+    match file_name.as_ref() {
+        "CMakeLists.txt" => return Some("cmake"),
+        "Dockerfile" => return Some("dockerfile"),
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("make"),
+        "Cargo.lock" => return Some("toml"),
+        name if name.starts_with(".env") => return Some("dotenv"),
+        _ => {}
+    }
+
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" | "pyi" => "python",
+        "php" => "php",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" | "ino" => "cpp",
+        "cmake" => "cmake",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "cs" => "csharp",
+        "swift" => "swift",
+        "sh" | "bash" | "zsh" => "shell",
+        "ps1" => "powershell",
+        "css" => "css",
+        "scss" => "scss",
+        "sass" => "sass",
+        "less" => "less",
+        "html" | "htm" => "html",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "md" => "markdown",
+        "mdx" => "mdx",
+        "json" => "json",
+        "toml" => "toml",
+        "yml" | "yaml" => "yaml",
+        "xml" => "xml",
+        "ini" => "ini",
+        "sql" => "sql",
+        "prisma" => "prisma",
+        "graphql" | "gql" => "graphql",
+        "proto" => "protobuf",
+        "txt" => "text",
+        _ => return None,
+    };
+    Some(language)
+}
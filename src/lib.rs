@@ -0,0 +1,57 @@
+//! CodeFlattener as a library: walk one or more directories, filter files by profile,
+//! extension and glob, and flatten them into a single document with token counts.
+//!
+//! The `code-flattener` binary is a thin CLI over [`Flattener`] and [`FlattenOptions`].
+
+pub mod config;
+pub mod profiles;
+pub mod wordpress_profile;
+
+mod budget;
+mod changed;
+mod comments;
+mod encoding;
+mod explain;
+mod filters;
+mod flattener;
+mod git;
+mod language;
+mod normalize;
+mod options;
+mod ordering;
+mod outline;
+mod patterns;
+mod redact;
+mod render;
+mod sink;
+mod split;
+mod stats;
+mod template;
+mod tokens;
+mod tree;
+mod truncate;
+
+pub use crate::encoding::EncodingPolicy;
+pub use crate::explain::{Decision, Explanation};
+pub use crate::filters::SkipReason;
+pub use crate::flattener::{
+    FileMetadata, FlattenResult, FlattenSummary, FlattenedFile, Flattener, OmittedFile, SkippedFile,
+};
+pub use crate::git::{GitChanges, RepositoryInfo};
+pub use crate::language::language_for_path;
+pub use crate::normalize::Normalization;
+pub use crate::options::FlattenOptions;
+pub use crate::ordering::SortOrder;
+pub use crate::patterns::PatternTarget;
+pub use crate::profiles::{Profile, ProfileManager};
+pub use crate::redact::{RedactRule, Redaction, RuleMatches};
+pub use crate::render::{
+    JsonRenderer, JsonlRenderer, MarkdownRenderer, OutputFormat, OutputHeader, OutputRenderer,
+    OutputTotals, PlainRenderer, XmlRenderer,
+};
+pub use crate::sink::{OutputSink, StringSink, WriterSink};
+pub use crate::split::{SplitLimit, SplitSink};
+pub use crate::stats::{StatsFormat, StatsRow, TokenStats};
+pub use crate::template::TemplateRenderer;
+pub use crate::tokens::{count_tokens, Tokenizer};
+pub use crate::truncate::{TruncateRule, Truncation};
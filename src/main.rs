@@ -1,26 +1,20 @@
 // src/main.rs
-mod config;
-mod profiles;
-mod wordpress_profile;
-
-use crate::config::ConfigFile;
-use crate::profiles::ProfileManager;
+use code_flattener::config::{self, ConfigFile};
+use code_flattener::{
+    EncodingPolicy, FileMetadata, FlattenOptions, Flattener, Normalization, OutputFormat, OutputSink, PatternTarget, ProfileManager, RedactRule, RuleMatches, SortOrder, SplitLimit,
+    SplitSink, StatsFormat, TokenStats, Tokenizer, TruncateRule, Truncation, WriterSink,
+};
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use glob::Pattern;
-use ignore::WalkBuilder;
-use rayon::prelude::*;
-use std::collections::HashSet;
 use std::fs;
-use std::io::{self, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::sync::{Arc, Mutex};
-use tiktoken_rs::p50k_base;
-use tracing::{info, warn, Level};
+use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 
+/// Rows shown per section of the `--stats` table; the JSON report lists everything.
+const STATS_TABLE_ROWS: usize = 20;
+
 #[derive(Parser, Debug)]
 #[command(
     author,
@@ -37,6 +31,16 @@ pub struct Args {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Split the output into parts of at most N tokens (out.part1.txt, out.part2.txt, ...)
+    /// plus an index of the files in each part (out.index.txt).
+    #[arg(long, value_name = "N", requires = "output", conflicts_with = "split_bytes")]
+    split_tokens: Option<usize>,
+
+    /// Split the output into parts of at most N bytes (out.part1.txt, out.part2.txt, ...)
+    /// plus an index of the files in each part (out.index.txt).
+    #[arg(long, value_name = "N", requires = "output")]
+    split_bytes: Option<usize>,
+
     /// Use a predefined profile for a specific project type.
     #[arg(short, long)]
     profile: Option<String>,
@@ -57,14 +61,33 @@ pub struct Args {
     #[arg(long, default_value_t = 2.0)]
     max_size: f64,
 
-    /// Format the output content using Markdown code blocks.
+    /// Format the output content using Markdown code blocks (same as --format markdown).
     #[arg(long, action = clap::ArgAction::Count)]
     markdown: u8,
 
-    /// Use GPT-4 tokenizer for more accurate token counting.
+    /// Output format: plain, markdown, json, jsonl or xml.
+    #[arg(long)]
+    format: Option<OutputFormat>,
+
+    /// Render output from a template file (overrides --format).
+    #[arg(long, value_name = "FILE")]
+    template: Option<PathBuf>,
+
+    /// Tokenizer for token counts: cl100k_base, o200k_base, p50k_base (default with
+    /// --max-tokens), estimate (characters / 4) or whitespace (default otherwise).
+    #[arg(long, value_name = "NAME")]
+    tokenizer: Option<Tokenizer>,
+
+    /// Count tokens with cl100k_base (GPT-4); same as --tokenizer cl100k_base.
     #[arg(long)]
     gpt4_tokens: bool,
 
+    /// Keep the output under N tokens: files are picked by priority (include globs, then
+    /// recently changed in git, then smaller files) and the rest listed at the end.
+    /// Counted with p50k_base unless --tokenizer says otherwise.
+    #[arg(long, value_name = "N")]
+    max_tokens: Option<usize>,
+
     /// Append a section with current Git status and diffs.
     #[arg(short = 'g', long = "include-git-changes")]
     include_git_changes: bool,
@@ -77,6 +100,33 @@ pub struct Args {
     #[arg(long, requires = "include_git_changes")]
     no_unstaged_diff: bool,
 
+    /// Append the last N commits (hash, date, author, subject) to the Git section
+    #[arg(long, value_name = "N")]
+    git_log: Option<usize>,
+
+    /// List the files changed by each commit of --git-log (git log --stat)
+    #[arg(long, requires = "git_log")]
+    git_log_stat: bool,
+
+    /// Append the diff of HEAD against a branch or commit (git diff <REF>...HEAD), limited
+    /// to the files that pass the filters
+    #[arg(long, value_name = "REF")]
+    git_diff_base: Option<String>,
+
+    /// Only flatten files added or modified since HEAD forked from a branch or commit,
+    /// uncommitted and untracked changes included
+    #[arg(long, value_name = "REF", group = "changed")]
+    changed_since: Option<String>,
+
+    /// Only flatten files with uncommitted changes (staged, unstaged or untracked)
+    #[arg(long, group = "changed")]
+    changed_only: bool,
+
+    /// With --changed-since or --changed-only, also flatten the other files in the
+    /// directories of changed files and the tests of changed files
+    #[arg(long, requires = "changed")]
+    changed_neighbors: bool,
+
     /// Print verbose output during processing.
     #[arg(short, long)]
     verbose: bool,
@@ -109,14 +159,18 @@ pub struct Args {
     #[arg(long, default_value_t = 100)]
     max_depth: usize,
 
-    /// Comma-separated list of patterns to exclude
+    /// Comma-separated list of regular expressions; matching files are skipped
     #[arg(long, value_delimiter = ',')]
     exclude_patterns: Option<Vec<String>>,
 
-    /// Comma-separated list of patterns to include
+    /// Comma-separated list of regular expressions; only matching files are included
     #[arg(long, value_delimiter = ',')]
     include_patterns: Option<Vec<String>>,
 
+    /// Match --include-patterns/--exclude-patterns against the relative path (default) or the file content
+    #[arg(long, value_name = "path|content")]
+    pattern_target: Option<PatternTarget>,
+
     /// Comma-separated list of glob patterns to exclude
     #[arg(long, value_delimiter = ',')]
     exclude_globs: Option<Vec<String>>,
@@ -129,14 +183,136 @@ pub struct Args {
     #[arg(long)]
     parallel: bool,
 
+    /// Output order: walk, path, size, mtime or churn (git commit count). Stable with --parallel.
+    #[arg(long)]
+    sort: Option<SortOrder>,
+
+    /// Print token, byte and line counts per file, directory and extension to stderr,
+    /// most expensive first, as a table (default) or JSON (`--stats=json`).
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "table")]
+    stats: Option<StatsFormat>,
+
+    /// Start the output with a directory tree of the included files.
+    #[arg(long)]
+    tree: bool,
+
+    /// Like --tree, but also list filtered-out files marked [excluded].
+    #[arg(long)]
+    tree_excluded: bool,
+
+    /// Print, for every file (or just PATH with `--explain=PATH`), whether it would be
+    /// included and the rule that decided it, then exit without writing any output.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, require_equals = true)]
+    explain: Option<Option<PathBuf>>,
+
+    /// Remove comments (Rust, C/C++, Go, Java, JS/TS, PHP, Python, CSS, shell, SQL, ...);
+    /// doc comments and docstrings are kept
+    #[arg(long)]
+    strip_comments: bool,
+
+    /// Remove doc comments and Python docstrings too (implies --strip-comments)
+    #[arg(long)]
+    strip_docstrings: bool,
+
+    /// Reduce Rust, JS/TS, PHP and Python files to their outline: declarations and
+    /// signatures, with function bodies elided as `{ ... }`
+    #[arg(long)]
+    outline: bool,
+
+    /// Comma-separated list of glob patterns of files to reduce to an outline
+    #[arg(long, value_delimiter = ',')]
+    outline_globs: Option<Vec<String>>,
+
+    /// Comma-separated list of glob patterns of files to keep in full with --outline
+    #[arg(long, value_delimiter = ',')]
+    full_globs: Option<Vec<String>>,
+
+    /// Keep the first and last lines of files over --max-size instead of skipping them:
+    /// HEAD:TAIL lines, or whole lines up to HEAD:TAIL tokens (e.g. 200:50, 2000:500tokens)
+    #[arg(long, value_name = "HEAD:TAIL[lines|tokens]")]
+    truncate: Option<Truncation>,
+
+    /// Comma-separated list of glob patterns of oversized files to truncate; others are skipped
+    #[arg(long, value_delimiter = ',', requires = "truncate")]
+    truncate_globs: Option<Vec<String>>,
+
+    /// Truncate oversized files matching GLOB with their own HEAD:TAIL spec, e.g.
+    /// '**/*.sql=300:50' (repeatable; the first matching rule wins over --truncate)
+    #[arg(long = "truncate-rule", value_name = "GLOB=HEAD:TAIL[lines|tokens]")]
+    truncate_rules: Option<Vec<TruncateRule>>,
+
+    /// Normalise file contents: LF line endings, no trailing whitespace, no runs of blank lines
+    #[arg(long)]
+    normalize: bool,
+
+    /// Convert CRLF and CR line endings to LF
+    #[arg(long, overrides_with = "no_normalize_line_endings")]
+    normalize_line_endings: bool,
+
+    /// Keep CRLF and CR line endings, even if --normalize or the config file converts them
+    #[arg(long, overrides_with = "normalize_line_endings")]
+    no_normalize_line_endings: bool,
+
+    /// Remove spaces and tabs at the end of lines
+    #[arg(long, overrides_with = "no_trim_trailing_whitespace")]
+    trim_trailing_whitespace: bool,
+
+    /// Keep trailing whitespace, even if --normalize or the config file removes it
+    #[arg(long, overrides_with = "trim_trailing_whitespace")]
+    no_trim_trailing_whitespace: bool,
+
+    /// Collapse runs of blank lines into one
+    #[arg(long, overrides_with = "no_collapse_blank_lines")]
+    collapse_blank_lines: bool,
+
+    /// Keep runs of blank lines, even if --normalize or the config file collapses them
+    #[arg(long, overrides_with = "collapse_blank_lines")]
+    no_collapse_blank_lines: bool,
+
+    /// Keep a leading byte order mark in file contents (stripped by default)
+    #[arg(long, overrides_with = "strip_bom")]
+    keep_bom: bool,
+
+    /// Strip a leading byte order mark, even if the config file keeps it
+    #[arg(long, overrides_with = "keep_bom")]
+    strip_bom: bool,
+
+    /// [normalize] table from the config file
+    #[arg(skip)]
+    normalize_config: Option<Normalization>,
+
+    /// Files that are not UTF-8 and have no byte order mark: transcode (guess the encoding,
+    /// default), lossy (replace invalid bytes), skip or error.
+    #[arg(long, value_name = "transcode|lossy|skip|error")]
+    encoding_fallback: Option<EncodingPolicy>,
+
+    /// End the output with a list of files skipped for their size, as binary, or because
+    /// they could not be read (which then no longer fails the run).
+    #[arg(long)]
+    list_skipped: bool,
+
+    /// Keep secrets (cloud keys, private keys, JWTs, WordPress keys, env passwords) instead
+    /// of replacing them with placeholders
+    #[arg(long)]
+    no_redact_secrets: bool,
+
+    /// Fail without writing anything if any file contains a secret
+    #[arg(long)]
+    fail_on_secrets: bool,
+
     /// Show progress bar
     #[arg(long)]
     progress: bool,
 
-    /// Dry run: log which files would be processed but don't read them
+    /// Dry run: log which files would be processed but don't read them (except to count
+    /// the matches of each [[redact]] rule, if there are any)
     #[arg(long)]
     dry_run: bool,
 
+    /// [[redact]] rules from the config file
+    #[arg(skip)]
+    redact_rules: Vec<RedactRule>,
+
     /// WordPress-profile-specific: comma-separated list of plugin slugs to exclude (e.g. woocommerce,elementor-pro)
     #[arg(long, value_delimiter = ',', use_value_delimiter = true)]
     wp_exclude_plugins: Option<Vec<String>>,
@@ -150,11 +326,92 @@ pub struct Args {
     wp_include_theme: Option<String>,
 }
 
-#[derive(Debug)]
-struct ProcessingResult {
-    content: String,
-    file_count: usize,
-    token_count: usize,
+impl Args {
+    /// The config file's `[normalize]` table (or the defaults) with each step given on the
+    /// command line switched on or off. `None` if neither sets anything, so that a
+    /// profile's table applies.
+    fn normalization(&self) -> Option<Normalization> {
+        let step = |on: bool, off: bool| if off { Some(false) } else if on { Some(true) } else { None };
+        let strip_bom = step(self.strip_bom, self.keep_bom);
+        let line_endings = step(self.normalize || self.normalize_line_endings, self.no_normalize_line_endings);
+        let trailing_whitespace =
+            step(self.normalize || self.trim_trailing_whitespace, self.no_trim_trailing_whitespace);
+        let blank_lines = step(self.normalize || self.collapse_blank_lines, self.no_collapse_blank_lines);
+        let given = [strip_bom, line_endings, trailing_whitespace, blank_lines];
+        if self.normalize_config.is_none() && given.iter().all(Option::is_none) {
+            return None;
+        }
+        let base = self.normalize_config.unwrap_or_default();
+        Some(Normalization {
+            strip_bom: strip_bom.unwrap_or(base.strip_bom),
+            line_endings: line_endings.unwrap_or(base.line_endings),
+            trailing_whitespace: trailing_whitespace.unwrap_or(base.trailing_whitespace),
+            blank_lines: blank_lines.unwrap_or(base.blank_lines),
+        })
+    }
+
+    fn into_options(self) -> FlattenOptions {
+        let normalize = self.normalization();
+        FlattenOptions {
+            target_dirs: self.target_dirs,
+            profile: self.profile,
+            extensions: self.extensions,
+            allowed_filenames: self.allowed_filenames,
+            max_size: self.max_size,
+            format: match self.format {
+                Some(format) => format,
+                None if self.markdown > 0 => OutputFormat::Markdown,
+                None => OutputFormat::Plain,
+            },
+            template: self.template,
+            tokenizer: self.tokenizer,
+            gpt4_tokens: self.gpt4_tokens,
+            max_tokens: self.max_tokens,
+            include_git_changes: self.include_git_changes,
+            no_staged_diff: self.no_staged_diff,
+            no_unstaged_diff: self.no_unstaged_diff,
+            git_log: self.git_log,
+            git_log_stat: self.git_log_stat,
+            git_diff_base: self.git_diff_base,
+            changed_since: self.changed_since,
+            changed_only: self.changed_only,
+            changed_neighbors: self.changed_neighbors,
+            verbose: self.verbose,
+            include_dirs: self.include_dirs,
+            exclude_dirs: self.exclude_dirs,
+            exclude_node_modules: self.exclude_node_modules,
+            exclude_build_dirs: self.exclude_build_dirs,
+            exclude_hidden_dirs: self.exclude_hidden_dirs,
+            max_depth: self.max_depth,
+            exclude_patterns: self.exclude_patterns,
+            include_patterns: self.include_patterns,
+            pattern_target: self.pattern_target,
+            exclude_globs: self.exclude_globs,
+            include_globs: self.include_globs,
+            parallel: self.parallel,
+            sort: self.sort.unwrap_or_default(),
+            tree: self.tree,
+            tree_excluded: self.tree_excluded,
+            strip_comments: self.strip_comments,
+            strip_docstrings: self.strip_docstrings,
+            outline: self.outline,
+            outline_globs: self.outline_globs,
+            full_globs: self.full_globs,
+            truncate: self.truncate,
+            truncate_globs: self.truncate_globs,
+            truncate_rules: self.truncate_rules,
+            normalize,
+            encoding_fallback: self.encoding_fallback.unwrap_or_default(),
+            list_skipped: self.list_skipped,
+            redact_secrets: !self.no_redact_secrets,
+            fail_on_secrets: self.fail_on_secrets,
+            redact_rules: self.redact_rules,
+            dry_run: self.dry_run,
+            wp_exclude_plugins: self.wp_exclude_plugins,
+            wp_include_only_plugins: self.wp_include_only_plugins,
+            wp_include_theme: self.wp_include_theme,
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -183,42 +440,109 @@ fn main() -> Result<()> {
     }
 
     // 4. Merge Config into Args
-    let mut args = merge_config_with_args(args_cli, &config);
-    validate_config(&args)?;
+    let args = merge_config_with_args(args_cli, &config);
+    let output = args.output.clone();
+    let split = match (args.split_tokens, args.split_bytes) {
+        (Some(tokens), _) => Some(SplitLimit::Tokens(tokens)),
+        (None, Some(bytes)) => Some(SplitLimit::Bytes(bytes)),
+        (None, None) => None,
+    };
+    let stats = args.stats;
+    let explain = args.explain.clone();
+    let flattener = Flattener::new(args.into_options()).with_profile_manager(profile_manager);
+    let options = flattener.resolved_options();
+    if split.is_some()
+        && options.template.is_none()
+        && matches!(options.format, OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Xml)
+    {
+        anyhow::bail!("--split-tokens/--split-bytes need plain or markdown output (or a --template)");
+    }
 
-    // 5. Process Directories
-    let result = process_directories(&mut args, &profile_manager)?;
+    if let Some(only) = explain {
+        return print_explanations(&flattener, only.as_deref());
+    }
 
-    // 6. Output Results
-    output_results(&result, &args)?;
+    // 5. Process Directories, streaming each file to the output as it is ready
+    let mut sink: Box<dyn OutputSink> = match (&output, split) {
+        (Some(path), Some(limit)) => {
+            Box::new(SplitSink::new(path, limit, options.resolved_tokenizer()))
+        }
+        (Some(path), None) => Box::new(WriterSink::create(path)?),
+        (None, _) => Box::new(WriterSink::stdout()),
+    };
+    let summary = flattener.run_with_sink(sink.as_mut())?;
 
+    if split.is_none()
+        && let Some(output_path) = &output
+    {
+        info!("Flattened code written to: {}", output_path.display());
+    }
     info!(
-        "Processing complete: {} files, {} tokens",
-        result.file_count, result.token_count
+        "Processing complete: {} files, {} tokens ({})",
+        summary.file_count, summary.token_count, options.resolved_tokenizer()
     );
 
+    print_redactions(&summary.files);
+    print_rule_matches(&summary.rule_matches);
+
+    if let Some(format) = stats {
+        let stats = TokenStats::from_files(&summary.files);
+        match format {
+            StatsFormat::Table => eprint!("{}", stats.to_table(STATS_TABLE_ROWS)),
+            StatsFormat::Json => eprintln!("{}", serde_json::to_string_pretty(&stats)?),
+        }
+    }
+
     Ok(())
 }
 
+/// Lists the secrets replaced by placeholders on stderr.
+fn print_redactions(files: &[FileMetadata]) {
+    let secrets = |file: &FileMetadata| file.redactions.iter().filter(|r| r.secret).count();
+    let count: usize = files.iter().map(secrets).sum();
+    if count == 0 {
+        return;
+    }
+    let file_count = files.iter().filter(|f| secrets(f) > 0).count();
+    eprintln!("Redacted {} secrets in {} files:", count, file_count);
+    for file in files {
+        for redaction in file.redactions.iter().filter(|r| r.secret) {
+            let path = file.relative_path.to_string_lossy().replace('\\', "/");
+            eprintln!("  {}:{}: {}", path, redaction.line, redaction.kind);
+        }
+    }
+}
+
+/// Reports how often each [[redact]] rule matched on stderr.
+fn print_rule_matches(rule_matches: &[RuleMatches]) {
+    if rule_matches.is_empty() {
+        return;
+    }
+    eprintln!("Redact rule matches:");
+    for count in rule_matches {
+        eprintln!("  {}: {} matches in {} files", count.rule, count.matches, count.files);
+    }
+}
+
 fn merge_config_with_args(mut args: Args, config: &Option<ConfigFile>) -> Args {
     if let Some(config) = config {
-        if args.profile.is_none() {
-            if let Some(profile) = &config.profile {
-                args.profile = Some(profile.clone());
-            }
+        if args.profile.is_none()
+            && let Some(profile) = &config.profile
+        {
+            args.profile = Some(profile.clone());
         }
 
-        if let Some(exts) = &config.extensions {
-            // Only override if not provided via CLI
-            if args.extensions.is_none() {
-                args.extensions = Some(exts.clone());
-            }
+        // Only override if not provided via CLI
+        if let Some(exts) = &config.extensions
+            && args.extensions.is_none()
+        {
+            args.extensions = Some(exts.clone());
         }
-        
-        if let Some(filenames) = &config.allowed_filenames {
-             if args.allowed_filenames.is_none() {
-                args.allowed_filenames = Some(filenames.clone());
-            }
+
+        if let Some(filenames) = &config.allowed_filenames
+            && args.allowed_filenames.is_none()
+        {
+            args.allowed_filenames = Some(filenames.clone());
         }
 
         if args.include_globs.is_none() {
@@ -227,628 +551,134 @@ fn merge_config_with_args(mut args: Args, config: &Option<ConfigFile>) -> Args {
         if args.exclude_globs.is_none() {
             args.exclude_globs = config.exclude_globs.clone();
         }
-        
-        if args.markdown == 0 {
-            if let Some(markdown) = config.markdown {
-                args.markdown = if markdown { 1 } else { 0 };
-            }
+        if args.include_patterns.is_none() {
+            args.include_patterns = config.include_patterns.clone();
         }
-        
-        // Merge boolean flags if CLI flag is false (default)
-        if !args.exclude_node_modules && config.exclude_node_modules.unwrap_or(false) {
-            args.exclude_node_modules = true;
+        if args.exclude_patterns.is_none() {
+            args.exclude_patterns = config.exclude_patterns.clone();
         }
-        if !args.exclude_build_dirs && config.exclude_build_dirs.unwrap_or(false) {
-            args.exclude_build_dirs = true;
+        if !args.strip_comments && config.strip_comments.unwrap_or(false) {
+            args.strip_comments = true;
         }
-        if !args.exclude_hidden_dirs && config.exclude_hidden_dirs.unwrap_or(false) {
-            args.exclude_hidden_dirs = true;
+        if !args.strip_docstrings && config.strip_docstrings.unwrap_or(false) {
+            args.strip_docstrings = true;
         }
-        if !args.include_git_changes && config.include_git_changes.unwrap_or(false) {
-            args.include_git_changes = true;
+        if !args.outline && config.outline.unwrap_or(false) {
+            args.outline = true;
         }
-    }
-    args
-}
-
-fn validate_config(args: &Args) -> Result<()> {
-    if let (Some(include_dirs), Some(exclude_dirs)) = (&args.include_dirs, &args.exclude_dirs) {
-        for include_dir in include_dirs {
-            for exclude_dir in exclude_dirs {
-                if exclude_dir.starts_with(include_dir) {
-                    return Err(anyhow::anyhow!(
-                        "Conflict: exclude directory '{}' is within include directory '{}'",
-                        exclude_dir.display(),
-                        include_dir.display()
-                    ));
-                }
-            }
+        if args.outline_globs.is_none() {
+            args.outline_globs = config.outline_globs.clone();
         }
-    }
-
-    if args.max_size > 100.0 {
-        return Err(anyhow::anyhow!("Max file size cannot exceed 100MB"));
-    }
-
-    Ok(())
-}
-
-fn process_directories(args: &mut Args, profile_manager: &ProfileManager) -> Result<ProcessingResult> {
-    // Apply Profile Settings
-    if let Some(profile_name) = &args.profile {
-        let profile = if profile_name == "wordpress" {
-             // Special handling for WordPress to enable path-aware resolution
-             let default_path = PathBuf::from(".");
-             let path = args.target_dirs.first().unwrap_or(&default_path);
-             profile_manager.resolve_wordpress_path_aware(profile_name, path, args)
-        } else {
-             profile_manager.resolve(profile_name)
-        };
-
-        if let Some(p) = profile {
-            if args.verbose {
-                info!("Applied profile: {}", p.description);
-            }
-            // Merge profile settings into args if args are empty
-            if args.extensions.is_none() {
-                args.extensions = Some(p.allowed_extensions);
-            }
-            if args.allowed_filenames.is_none() {
-                args.allowed_filenames = Some(p.allowed_filenames);
-            }
-            // Append globs from profile to any existing args
-            if !p.include_globs.is_empty() {
-                 let mut current_globs = args.include_globs.clone().unwrap_or_default();
-                 for g in p.include_globs {
-                     if !current_globs.contains(&g) {
-                         current_globs.push(g);
-                     }
-                 }
-                 args.include_globs = Some(current_globs);
-            }
-            
-            if args.markdown == 0 {
-                if let Some(markdown) = p.markdown {
-                    args.markdown = if markdown { 1 } else { 0 };
-                }
-            }
-
-            // Merge additional profile settings
-            if args.max_size == 0.0 {
-                if let Some(max_size) = p.max_size {
-                    args.max_size = max_size;
-                }
-            }
-            if !args.gpt4_tokens {
-                if let Some(gpt4_tokens) = p.gpt4_tokens {
-                    args.gpt4_tokens = gpt4_tokens;
-                }
-            }
-            if !args.include_git_changes {
-                if let Some(include_git_changes) = p.include_git_changes {
-                    args.include_git_changes = include_git_changes;
-                }
-            }
-            if !args.no_staged_diff {
-                if let Some(no_staged_diff) = p.no_staged_diff {
-                    args.no_staged_diff = no_staged_diff;
-                }
-            }
-            if !args.no_unstaged_diff {
-                if let Some(no_unstaged_diff) = p.no_unstaged_diff {
-                    args.no_unstaged_diff = no_unstaged_diff;
-                }
-            }
-            if args.include_dirs.is_none() {
-                args.include_dirs = p.include_dirs.clone();
-            }
-            if args.exclude_dirs.is_none() {
-                args.exclude_dirs = p.exclude_dirs.clone();
-            }
-            if args.exclude_patterns.is_none() {
-                args.exclude_patterns = p.exclude_patterns.clone();
-            }
-            if args.include_patterns.is_none() {
-                args.include_patterns = p.include_patterns.clone();
-            }
-            if args.exclude_globs.is_none() {
-                args.exclude_globs = p.exclude_globs.clone();
-            }
-            if !args.exclude_node_modules {
-                if let Some(exclude_node_modules) = p.exclude_node_modules {
-                    args.exclude_node_modules = exclude_node_modules;
-                }
-            }
-            if !args.exclude_build_dirs {
-                if let Some(exclude_build_dirs) = p.exclude_build_dirs {
-                    args.exclude_build_dirs = exclude_build_dirs;
-                }
-            }
-            if !args.exclude_hidden_dirs {
-                if let Some(exclude_hidden_dirs) = p.exclude_hidden_dirs {
-                    args.exclude_hidden_dirs = exclude_hidden_dirs;
-                }
-            }
-            if args.max_depth == 0 {
-                if let Some(max_depth) = p.max_depth {
-                    args.max_depth = max_depth;
-                }
-            }
-        } else {
-            warn!("Profile '{}' not found. Using provided arguments only.", profile_name);
+        if args.full_globs.is_none() {
+            args.full_globs = config.full_globs.clone();
         }
-    }
-
-    info!(
-        "Settings - extensions: {:?}, filenames: {:?}, include_globs: {:?}, max_size: {}MB",
-        args.extensions, args.allowed_filenames, args.include_globs, args.max_size
-    );
-
-    // Prepare lookup sets
-    let mut extensions: HashSet<String> = HashSet::new();
-    if let Some(exts) = &args.extensions {
-        extensions = exts
-            .iter()
-            .map(|e| if e.starts_with('.') { e.clone() } else { format!(".{}", e) })
-            .collect();
-    }
-    let mut allowed_filenames: HashSet<String> = HashSet::new();
-    if let Some(files) = &args.allowed_filenames {
-        allowed_filenames = files.iter().cloned().collect();
-    }
-
-    if extensions.is_empty() && allowed_filenames.is_empty() && args.include_globs.is_none() {
-        return Err(anyhow::anyhow!(
-            "No allowed extensions, filenames, or include globs specified"
-        ));
-    }
-
-    let max_file_size = (args.max_size * 1024.0 * 1024.0) as u64;
-    let all_contents = Arc::new(Mutex::new(String::new()));
-    let file_count = Arc::new(Mutex::new(0));
-
-    info!("Starting processing...");
-
-    for start_dir in &args.target_dirs {
-        let start_dir = fs::canonicalize(start_dir)
-            .with_context(|| format!("Failed to canonicalize path: {}", start_dir.display()))?;
-
-        if !is_safe_path(&start_dir, &start_dir) {
-             return Err(anyhow::anyhow!("Path traversal detected: {}", start_dir.display()));
-        }
-
-        let walker = build_walker(&start_dir, args);
-        let entries: Vec<_> = walker.build().filter_map(Result::ok).collect();
-
-        if args.parallel {
-            entries.par_iter().for_each(|entry| {
-                let path = entry.path();
-                if should_process_path(path, args, &start_dir) {
-                    if let Err(e) = process_single_file(
-                        path,
-                        &extensions,
-                        &allowed_filenames,
-                        max_file_size,
-                        args,
-                        &all_contents,
-                        &file_count,
-                    ) {
-                        warn!("Failed to process {}: {}", path.display(), e);
-                    }
-                }
-            });
-        } else {
-            for entry in entries {
-                let path = entry.path();
-                if should_process_path(path, args, &start_dir) {
-                    process_single_file(
-                        path,
-                        &extensions,
-                        &allowed_filenames,
-                        max_file_size,
-                        args,
-                        &all_contents,
-                        &file_count,
-                    )?;
-                }
-            }
+        if args.truncate.is_none() {
+            args.truncate = config.truncate;
         }
-    }
-
-    // Final content assembly
-    let content = if args.dry_run {
-        String::new()
-    } else {
-        let mut git_output = String::new();
-        if args.include_git_changes {
-            if let Ok(Some(root)) =
-                find_git_root(args.target_dirs.first().unwrap_or(&PathBuf::from(".")))
-            {
-                if let Ok(Some(output)) = get_git_changes(
-                    &root,
-                    !args.no_staged_diff,
-                    !args.no_unstaged_diff,
-                    args.verbose,
-                ) {
-                    git_output = output;
-                }
-            }
-        }
-
-        let mut content = all_contents.lock().unwrap().clone();
-        content.push_str(&git_output);
-        content
-    };
-
-    let token_count = if args.gpt4_tokens {
-        p50k_base()
-            .unwrap()
-            .encode_with_special_tokens(&content)
-            .len()
-    } else {
-        content.split_whitespace().count()
-    };
-
-    Ok(ProcessingResult {
-        content,
-        file_count: *file_count.lock().unwrap(),
-        token_count,
-    })
-}
-
-fn build_walker(start_dir: &Path, args: &Args) -> WalkBuilder {
-    let mut walker = WalkBuilder::new(start_dir);
-    walker.max_depth(Some(args.max_depth));
-
-    if args.exclude_node_modules {
-        walker.filter_entry(|entry| entry.file_name() != "node_modules");
-    }
-
-    if args.exclude_build_dirs {
-        walker.filter_entry(|entry| {
-            let name = entry.file_name().to_string_lossy();
-            !matches!(name.as_ref(), "target" | "build" | "dist")
-        });
-    }
-
-    if args.exclude_hidden_dirs {
-        walker.filter_entry(|entry| {
-            !entry.file_name().to_string_lossy().starts_with('.')
-        });
-    }
-
-    // Always filter specific WP dirs to avoid massive dumps unless explicitly crawled
-    walker.filter_entry(|entry| {
-        let name = entry.file_name().to_string_lossy();
-        name != "wp-admin" && name != "wp-includes"
-    });
-
-    walker
-}
-
-fn should_process_path(path: &Path, args: &Args, base_dir: &Path) -> bool {
-    if path.is_dir() { return false; }
-
-    let relative_path = match path.strip_prefix(base_dir) {
-        Ok(p) => p,
-        Err(_) => path,
-    };
-
-    if is_ignored_by_file(path, base_dir) { return false; }
-
-    // Directory Exclusions
-    if let Some(exclude_dirs) = &args.exclude_dirs {
-        for exclude_dir in exclude_dirs {
-            if relative_path.starts_with(exclude_dir) { return false; }
+        if args.truncate_globs.is_none() {
+            args.truncate_globs = config.truncate_globs.clone();
         }
-    }
-
-    // Directory Inclusions (Exclusive)
-    if let Some(include_dirs) = &args.include_dirs {
-        let mut included = false;
-        for include_dir in include_dirs {
-            if relative_path.starts_with(include_dir) {
-                included = true;
-                break;
-            }
+        if args.truncate_rules.is_none() {
+            args.truncate_rules = config.truncate_rules.clone();
         }
-        if !included { return false; }
-    }
-
-    // Exclude Globs
-    if let Some(exclude_globs) = &args.exclude_globs {
-        for pattern in exclude_globs {
-            // Check matches against OS path and forward-slash normalized path
-            if match_glob(pattern, relative_path) { return false; }
+        args.normalize_config = config.normalize;
+        if args.encoding_fallback.is_none() {
+            args.encoding_fallback = config.encoding_fallback;
         }
-    }
-
-    // Include Globs
-    if let Some(include_globs) = &args.include_globs {
-        let mut matches = false;
-        for pattern in include_globs {
-             if match_glob(pattern, relative_path) {
-                matches = true;
-                break;
-            }
+        if args.pattern_target.is_none() {
+            args.pattern_target = config.pattern_target;
         }
-        if !matches {
-            return false;
+        
+        if args.tokenizer.is_none() && !args.gpt4_tokens {
+            args.tokenizer = config.tokenizer;
         }
-    }
-
-    // WordPress-specific Exclusions
-    if let Some(excludes) = &args.wp_exclude_plugins {
-        if let Ok(rel) = path.strip_prefix(base_dir) {
-            let rel_str = rel.to_string_lossy().to_lowercase();
-            for raw in excludes {
-                let slug = raw.split('/').next().unwrap_or(raw).to_lowercase();
-                let plugin_prefix = format!("wp-content/plugins/{}", slug);
-                if rel_str.starts_with(&plugin_prefix) { return false; }
-            }
+        if !args.gpt4_tokens && config.gpt4_tokens.unwrap_or(false) {
+            args.gpt4_tokens = true;
         }
-    }
 
-    if is_binary_file(path) { return false; }
-
-    // WordPress Inclusion Logic (Strict Mode)
-    if args.profile.as_deref() == Some("wordpress") 
-       && (args.wp_include_only_plugins.is_some() || args.wp_include_theme.is_some()) {
-        if let Ok(rel) = path.strip_prefix(base_dir) {
-            let rel_str_lower = rel.to_string_lossy().to_lowercase();
-            if rel_str_lower == "wp-config.php" { return true; }
-            
-            if let Some(includes) = &args.wp_include_only_plugins {
-                for raw in includes {
-                    let slug = raw.split('/').next().unwrap_or(raw).to_lowercase();
-                    let prefix = format!("wp-content/plugins/{}", slug);
-                    if rel_str_lower.starts_with(&prefix) { return true; }
-                }
-            }
-            
-            if let Some(theme) = &args.wp_include_theme {
-                 let prefix = format!("wp-content/themes/{}", theme.to_lowercase());
-                 if rel_str_lower.starts_with(&prefix) { return true; }
-            }
-            return false; // Strict mode active and no match
+        if args.max_tokens.is_none() {
+            args.max_tokens = config.max_tokens;
         }
-    }
-
-    // Core WP File Exclusion
-    if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-        let core_wp_files = [
-            "xmlrpc.php", "wp-activate.php", "wp-cron.php", "wp-load.php",
-            "wp-blog-header.php", "wp-settings.php", "wp-login.php", "wp-signup.php",
-            "wp-trackback.php", "wp-comments-post.php", "wp-links-opml.php", "wp-mail.php",
-        ];
-        if core_wp_files.contains(&file_name) { return false; }
-    }
-
-    true
-}
 
-fn match_glob(pattern: &str, path: &Path) -> bool {
-    let pat_os = pattern.replace('/', &std::path::MAIN_SEPARATOR.to_string());
-    if let Ok(glob) = Pattern::new(&pat_os) {
-        if glob.matches_path(path) { return true; }
-    }
-    // Fallback for Windows: match against forward-slash string
-    let rel_forward = path.to_string_lossy().replace('\\', "/");
-    if let Ok(glob) = Pattern::new(pattern) {
-        if glob.matches_path(Path::new(&rel_forward)) { return true; }
-    }
-    false
-}
-
-fn is_ignored_by_file(path: &Path, base_dir: &Path) -> bool {
-    let patterns = load_ignore_patterns();
-    let relative_path = match path.strip_prefix(base_dir) {
-        Ok(p) => p,
-        Err(_) => path,
-    };
-    patterns.iter().any(|p| p.matches_path(relative_path))
-}
-
-fn load_ignore_patterns() -> Vec<Pattern> {
-    let mut patterns = Vec::new();
-    if let Ok(content) = fs::read_to_string(".flattenerignore") {
-        for line in content.lines() {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') {
-                if let Ok(p) = Pattern::new(line) { patterns.push(p); }
-            }
+        if args.sort.is_none() {
+            args.sort = config.sort;
         }
-    }
-    patterns
-}
 
-fn is_binary_file(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        let binary_extensions = [
-            "png", "jpg", "jpeg", "gif", "ico", "webp", "svg", "bmp", "tiff", "tif", "mp4", "avi",
-            "mov", "wmv", "flv", "webm", "mkv", "mp3", "wav", "ogg", "zip", "tar", "gz", "bz2",
-            "7z", "rar", "pdf", "doc", "docx", "xls", "xlsx", "exe", "dll", "so", "dylib", "woff",
-            "woff2", "ttf", "eot",
-        ];
-        if binary_extensions.contains(&ext_str.as_str()) { return true; }
-    }
-    // Byte check
-    if let Ok(mut file) = fs::File::open(path) {
-        let mut buffer = [0u8; 1024];
-        if let Ok(n) = file.read(&mut buffer) {
-            for &byte in &buffer[..n] {
-                if byte == 0 || (byte < 32 && byte != 9 && byte != 10 && byte != 13) {
-                    return true;
-                }
-            }
+        if args.format.is_none() && args.markdown == 0 {
+            args.format = config.format;
         }
-    }
-    false
-}
-
-fn process_single_file(
-    path: &Path,
-    extensions: &HashSet<String>,
-    allowed_filenames: &HashSet<String>,
-    max_file_size: u64,
-    args: &Args,
-    all_contents: &Arc<Mutex<String>>,
-    file_count: &Arc<Mutex<usize>>,
-) -> Result<()> {
-    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-    let extension = path.extension().unwrap_or_default().to_string_lossy();
-
-    // Logic: Allowed if extension matches OR filename matches OR include_globs matches.
-    // However, include_globs matching happens in `should_process_path` (mostly). 
-    // To support profiles that *only* have include_globs (no extensions), we must be permissive here 
-    // if include_globs are present.
-    
-    let is_allowed_ext = extensions.contains(&format!(".{}", extension));
-    let is_allowed_file = allowed_filenames.contains(file_name.as_ref());
-    let is_allowed_by_glob = args.include_globs.is_some();
-
-    if !is_allowed_ext && !is_allowed_file && !is_allowed_by_glob {
-        return Ok(());
-    }
-
-    if args.dry_run {
-        info!("DRY-RUN: would process {}", path.display());
-        let mut c = file_count.lock().unwrap();
-        *c += 1;
-        return Ok(());
-    }
-
-    let metadata = fs::metadata(path)
-        .with_context(|| format!("Failed to get metadata for {}", path.display()))?;
-
-    if metadata.len() > max_file_size {
-        if args.verbose { info!("Skipping large file: {}", path.display()); }
-        return Ok(());
-    }
 
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file {}", path.display()))?;
-
-    let file_path_str = path.to_string_lossy();
-    let mut formatted_content = if args.markdown > 0 {
-        format!("\n\n```{}\n# --- File: {} ---\n", extension, file_path_str)
-    } else {
-        format!("\n\n# --- File: {} ---\n\n", file_path_str)
-    };
-
-    formatted_content.push_str(&content);
-
-    if args.markdown > 0 {
-        formatted_content.push_str("\n```\n");
-    }
+        if args.template.is_none() {
+            args.template = config.template.clone();
+        }
 
-    let mut ac = all_contents.lock().unwrap();
-    ac.push_str(&formatted_content);
-    
-    let mut c = file_count.lock().unwrap();
-    *c += 1;
+        if args.markdown == 0
+            && let Some(markdown) = config.markdown
+        {
+            args.markdown = if markdown { 1 } else { 0 };
+        }
 
-    if args.verbose { info!("Processed: {}", path.display()); }
-    Ok(())
-}
 
-fn output_results(result: &ProcessingResult, args: &Args) -> Result<()> {
-    if let Some(output_path) = &args.output {
-        if let Some(parent) = output_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)?;
-            }
+        // Merge boolean flags if CLI flag is false (default)
+        if !args.exclude_node_modules && config.exclude_node_modules.unwrap_or(false) {
+            args.exclude_node_modules = true;
         }
-        let file = fs::File::create(output_path)?;
-        let mut writer = BufWriter::new(file);
-        write!(writer, "{}", result.content)?;
-        info!("Flattened code written to: {}", output_path.display());
-    } else {
-        let mut stdout = io::stdout().lock();
-        write!(stdout, "{}", result.content)?;
-    }
-    Ok(())
-}
-
-fn find_git_root(start_path: &Path) -> Result<Option<PathBuf>> {
-    let mut current_path = fs::canonicalize(start_path)?;
-    loop {
-        if current_path.join(".git").is_dir() {
-            return Ok(Some(current_path));
+        if !args.exclude_build_dirs && config.exclude_build_dirs.unwrap_or(false) {
+            args.exclude_build_dirs = true;
+        }
+        if !args.exclude_hidden_dirs && config.exclude_hidden_dirs.unwrap_or(false) {
+            args.exclude_hidden_dirs = true;
+        }
+        if !args.include_git_changes && config.include_git_changes.unwrap_or(false) {
+            args.include_git_changes = true;
+        }
+        if args.git_log.is_none() {
+            args.git_log = config.git_log;
+        }
+        if !args.git_log_stat && config.git_log_stat.unwrap_or(false) {
+            args.git_log_stat = true;
+        }
+        if args.git_diff_base.is_none() {
+            args.git_diff_base = config.git_diff_base.clone();
+        }
+        if !args.tree && config.tree.unwrap_or(false) {
+            args.tree = true;
+        }
+        if !args.tree_excluded && config.tree_excluded.unwrap_or(false) {
+            args.tree_excluded = true;
+        }
+        if !args.list_skipped && config.list_skipped.unwrap_or(false) {
+            args.list_skipped = true;
+        }
+        if !args.no_redact_secrets && config.redact_secrets == Some(false) {
+            args.no_redact_secrets = true;
         }
-        if !current_path.pop() {
-            return Ok(None);
+        if !args.fail_on_secrets && config.fail_on_secrets.unwrap_or(false) {
+            args.fail_on_secrets = true;
+        }
+        if let Some(rules) = &config.redact {
+            args.redact_rules = rules.clone();
         }
     }
+    args
 }
 
-fn get_git_changes(
-    repo_path: &Path,
-    include_staged: bool,
-    include_unstaged: bool,
-    verbose: bool,
-) -> Result<Option<String>> {
-    let mut output = String::new();
-    output.push_str("\n\n# --- Git Changes ---\n");
-    output.push_str(&format!("# Repository: {}\n\n", repo_path.display()));
-
-    let status_out = Command::new("git")
-        .args(["status", "--porcelain", "-uall"])
-        .current_dir(repo_path)
-        .output()?;
-
-    if status_out.status.success() {
-        let s = String::from_utf8_lossy(&status_out.stdout);
-        if !s.trim().is_empty() {
-            output.push_str("## Git Status:\n```bash\n");
-            output.push_str(s.trim());
-            output.push_str("\n```\n\n");
-        }
-    } else if verbose {
-        warn!("git status failed");
-    }
-
-    if include_staged {
-        let diff = Command::new("git")
-            .args(["diff", "--staged"])
-            .current_dir(repo_path)
-            .output()?;
-        if diff.status.success() {
-             let s = String::from_utf8_lossy(&diff.stdout);
-             if !s.trim().is_empty() {
-                 output.push_str("## Git Diff (Staged):\n```diff\n");
-                 output.push_str(s.trim());
-                 output.push_str("\n```\n\n");
-             }
-        }
-    }
-    
-    if include_unstaged {
-        let diff = Command::new("git")
-            .args(["diff"])
-            .current_dir(repo_path)
-            .output()?;
-        if diff.status.success() {
-             let s = String::from_utf8_lossy(&diff.stdout);
-             if !s.trim().is_empty() {
-                 output.push_str("## Git Diff (Unstaged):\n```diff\n");
-                 output.push_str(s.trim());
-                 output.push_str("\n```\n\n");
-             }
+fn print_explanations(flattener: &Flattener, only: Option<&Path>) -> Result<()> {
+    let explanations = flattener.explain()?;
+    let Some(only) = only else {
+        for explanation in &explanations {
+            println!("{}", explanation);
         }
-    }
+        return Ok(());
+    };
 
-    Ok(Some(output))
+    let only = fs::canonicalize(only)
+        .with_context(|| format!("Failed to canonicalize path: {}", only.display()))?;
+    // A file inside a skipped directory is explained by the directory's entry
+    let explanation = explanations
+        .iter()
+        .find(|e| e.path == only || (e.is_dir && only.starts_with(&e.path)))
+        .with_context(|| format!("{} is not under any target directory", only.display()))?;
+    println!("{}", explanation);
+    Ok(())
 }
-
-fn is_safe_path(path: &Path, base_dir: &Path) -> bool {
-    if path.strip_prefix(base_dir).is_ok() { return true; }
-    let base_abs = base_dir.canonicalize().unwrap_or_else(|_| base_dir.to_path_buf());
-    if path.starts_with(&base_abs) { return true; }
-    if !path.is_absolute() {
-        let candidate = base_abs.join(path);
-        return candidate.starts_with(&base_abs);
-    }
-    false
-}
\ No newline at end of file
@@ -0,0 +1,92 @@
+use serde::Deserialize;
+
+/// Clean-up steps applied to every file's content after it is read and decoded, before
+/// it is rendered and counted. Only the BOM is stripped by default.
+///
+/// In the config file and in custom profiles this is a `[normalize]` table:
+///
+/// ```toml
+/// [normalize]
+/// line_endings = true
+/// trailing_whitespace = true
+/// blank_lines = true
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Normalization {
+    /// Remove a leading byte order mark (U+FEFF).
+    pub strip_bom: bool,
+    /// Turn CRLF and lone CR line endings into LF.
+    pub line_endings: bool,
+    /// Remove spaces and tabs at the end of every line.
+    pub trailing_whitespace: bool,
+    /// Collapse runs of blank (or whitespace-only) lines into a single blank line.
+    pub blank_lines: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            strip_bom: true,
+            line_endings: false,
+            trailing_whitespace: false,
+            blank_lines: false,
+        }
+    }
+}
+
+impl Normalization {
+    /// Every step enabled.
+    pub fn all() -> Self {
+        Self {
+            strip_bom: true,
+            line_endings: true,
+            trailing_whitespace: true,
+            blank_lines: true,
+        }
+    }
+
+    /// Applies the enabled steps in order: BOM, line endings, trailing whitespace,
+    /// blank lines.
+    pub fn apply(&self, content: String) -> String {
+        let mut content = content;
+        if self.strip_bom && content.starts_with('\u{FEFF}') {
+            content.drain(..'\u{FEFF}'.len_utf8());
+        }
+        if self.line_endings && content.contains('\r') {
+            content = content.replace("\r\n", "\n").replace('\r', "\n");
+        }
+        if !self.trailing_whitespace && !self.blank_lines {
+            return content;
+        }
+
+        let mut out = String::with_capacity(content.len());
+        let mut previous_blank = false;
+        for line in content.split_inclusive('\n') {
+            let (text, ending) = split_line_ending(line);
+            let blank = text.trim().is_empty();
+            if self.blank_lines && blank && previous_blank {
+                continue;
+            }
+            previous_blank = blank;
+            if self.trailing_whitespace {
+                out.push_str(text.trim_end_matches([' ', '\t']));
+            } else {
+                out.push_str(text);
+            }
+            out.push_str(ending);
+        }
+        out
+    }
+}
+
+/// Splits a line from `split_inclusive('\n')` into its text and its `\n` / `\r\n` ending.
+fn split_line_ending(line: &str) -> (&str, &str) {
+    if let Some(text) = line.strip_suffix("\r\n") {
+        (text, "\r\n")
+    } else if let Some(text) = line.strip_suffix('\n') {
+        (text, "\n")
+    } else {
+        (line, "")
+    }
+}
@@ -0,0 +1,750 @@
+use crate::encoding::EncodingPolicy;
+use crate::normalize::Normalization;
+use crate::ordering::SortOrder;
+use crate::patterns::PatternTarget;
+use crate::profiles::{Profile, ProfileManager};
+use crate::redact::RedactRule;
+use crate::render::OutputFormat;
+use crate::tokens::Tokenizer;
+use crate::truncate::{TruncateRule, Truncation};
+use anyhow::Result;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// All settings that control a flattening run, independent of how they were collected
+/// (CLI, config file or library caller).
+///
+/// Fields are public so callers can fill them directly; the chainable setters below
+/// offer a builder-style alternative.
+#[derive(Debug, Clone)]
+pub struct FlattenOptions {
+    pub target_dirs: Vec<PathBuf>,
+    pub profile: Option<String>,
+    pub extensions: Option<Vec<String>>,
+    pub allowed_filenames: Option<Vec<String>>,
+    pub max_size: f64,
+    pub format: OutputFormat,
+    /// Template file used instead of the built-in renderer for `format`.
+    pub template: Option<PathBuf>,
+    /// Tokenizer used for all token counts; see [`FlattenOptions::resolved_tokenizer`].
+    pub tokenizer: Option<Tokenizer>,
+    /// Legacy switch for `cl100k_base` counting when no `tokenizer` is set.
+    pub gpt4_tokens: bool,
+    /// Token budget for the whole output, git section and list of omitted files included;
+    /// files that do not fit are omitted by priority and listed at the end of the output.
+    pub max_tokens: Option<usize>,
+    pub include_git_changes: bool,
+    pub no_staged_diff: bool,
+    pub no_unstaged_diff: bool,
+    /// Add the last N commits (hash, date, author, subject) to the git section, with or
+    /// without `include_git_changes`.
+    pub git_log: Option<usize>,
+    /// List the files changed by each commit of `git_log`.
+    pub git_log_stat: bool,
+    /// Add `git diff <ref>...HEAD` to the git section, the changes made since HEAD forked
+    /// from this branch or commit, limited to the files that pass the filters.
+    pub git_diff_base: Option<String>,
+    /// Only flatten files added or modified since this branch or commit forked from HEAD,
+    /// uncommitted and untracked changes included.
+    pub changed_since: Option<String>,
+    /// Only flatten files with uncommitted changes (staged, unstaged or untracked).
+    pub changed_only: bool,
+    /// With `changed_since` or `changed_only`, also flatten the other files in the
+    /// directories of changed files, and the tests of changed files.
+    pub changed_neighbors: bool,
+    pub verbose: bool,
+    pub include_dirs: Option<Vec<PathBuf>>,
+    pub exclude_dirs: Option<Vec<PathBuf>>,
+    pub exclude_node_modules: bool,
+    pub exclude_build_dirs: bool,
+    pub exclude_hidden_dirs: bool,
+    pub max_depth: usize,
+    /// Regular expressions; a file matching any of them is skipped.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Regular expressions; when set, a file must match at least one of them.
+    pub include_patterns: Option<Vec<String>>,
+    /// Whether the patterns above match the relative path (default) or the file content;
+    /// `None` uses the profile's.
+    pub pattern_target: Option<PatternTarget>,
+    pub exclude_globs: Option<Vec<String>>,
+    pub include_globs: Option<Vec<String>>,
+    pub parallel: bool,
+    pub sort: SortOrder,
+    /// Start the output with a directory tree of the included files.
+    pub tree: bool,
+    /// Also list walked files that were filtered out in the tree, marked `[excluded]`.
+    /// Implies `tree`.
+    pub tree_excluded: bool,
+    /// Remove comments from files in languages the comment lexer knows (Rust, C/C++,
+    /// JS/TS, PHP, Python, CSS, shell, ...). Documentation comments are kept.
+    pub strip_comments: bool,
+    /// Also remove documentation comments and Python docstrings. Implies `strip_comments`.
+    pub strip_docstrings: bool,
+    /// Reduce every Rust, JS/TS, PHP and Python file to an outline: declarations and
+    /// signatures, with function bodies elided.
+    pub outline: bool,
+    /// Reduce only the files matching these globs to an outline.
+    pub outline_globs: Option<Vec<String>>,
+    /// Files matching these globs keep their full content, overriding `outline` and
+    /// `outline_globs`.
+    pub full_globs: Option<Vec<String>>,
+    /// Keep the head and tail of files over `max_size` instead of skipping them.
+    pub truncate: Option<Truncation>,
+    /// Only truncate oversized files matching these globs; others are still skipped.
+    pub truncate_globs: Option<Vec<String>>,
+    /// Truncations for oversized files matching a glob; the first matching rule wins over
+    /// `truncate` and `truncate_globs`.
+    pub truncate_rules: Option<Vec<TruncateRule>>,
+    /// Clean-up applied to every file's content before it is rendered; `None` uses the
+    /// profile's, or `Normalization::default()`.
+    pub normalize: Option<Normalization>,
+    /// What to do with files that are neither UTF-8 nor start with a byte order mark.
+    pub encoding_fallback: EncodingPolicy,
+    /// End the output with a list of files skipped for their size, as binary, or because
+    /// they could not be read. Unreadable files are then skipped in sequential runs too,
+    /// instead of failing the run.
+    pub list_skipped: bool,
+    /// Replace secrets (cloud keys, private keys, JWTs, WordPress keys and passwords,
+    /// secret-looking env values) with placeholders. On by default.
+    pub redact_secrets: bool,
+    /// Fail the run, before anything is written, if any file contains a secret.
+    pub fail_on_secrets: bool,
+    /// User-defined `[[redact]]` rules, applied whether or not `redact_secrets` is set.
+    /// Rules of the profile are added after these.
+    pub redact_rules: Vec<RedactRule>,
+    pub dry_run: bool,
+    pub wp_exclude_plugins: Option<Vec<String>>,
+    pub wp_include_only_plugins: Option<Vec<String>>,
+    pub wp_include_theme: Option<String>,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            target_dirs: vec![PathBuf::from(".")],
+            profile: None,
+            extensions: None,
+            allowed_filenames: None,
+            max_size: 2.0,
+            format: OutputFormat::Plain,
+            template: None,
+            tokenizer: None,
+            gpt4_tokens: false,
+            max_tokens: None,
+            include_git_changes: false,
+            no_staged_diff: false,
+            no_unstaged_diff: false,
+            git_log: None,
+            git_log_stat: false,
+            git_diff_base: None,
+            changed_since: None,
+            changed_only: false,
+            changed_neighbors: false,
+            verbose: false,
+            include_dirs: None,
+            exclude_dirs: None,
+            exclude_node_modules: false,
+            exclude_build_dirs: false,
+            exclude_hidden_dirs: false,
+            max_depth: 100,
+            exclude_patterns: None,
+            include_patterns: None,
+            pattern_target: None,
+            exclude_globs: None,
+            include_globs: None,
+            parallel: false,
+            sort: SortOrder::Walk,
+            tree: false,
+            tree_excluded: false,
+            strip_comments: false,
+            strip_docstrings: false,
+            outline: false,
+            outline_globs: None,
+            full_globs: None,
+            truncate: None,
+            truncate_globs: None,
+            truncate_rules: None,
+            normalize: None,
+            encoding_fallback: EncodingPolicy::Transcode,
+            list_skipped: false,
+            redact_secrets: true,
+            fail_on_secrets: false,
+            redact_rules: Vec::new(),
+            dry_run: false,
+            wp_exclude_plugins: None,
+            wp_include_only_plugins: None,
+            wp_include_theme: None,
+        }
+    }
+}
+
+fn strings<I, S>(values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    values.into_iter().map(Into::into).collect()
+}
+
+fn paths<I, P>(values: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    values.into_iter().map(Into::into).collect()
+}
+
+impl FlattenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the list of directories to scan.
+    pub fn target_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.target_dirs = paths(dirs);
+        self
+    }
+
+    /// Replaces the list of directories to scan with a single directory.
+    pub fn target_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.target_dirs = vec![dir.into()];
+        self
+    }
+
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = Some(strings(extensions));
+        self
+    }
+
+    pub fn allowed_filenames<I, S>(mut self, filenames: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_filenames = Some(strings(filenames));
+        self
+    }
+
+    /// Maximum file size to process in megabytes (MB).
+    pub fn max_size(mut self, megabytes: f64) -> Self {
+        self.max_size = megabytes;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Renders output from a template file instead of the built-in format.
+    pub fn template(mut self, path: impl Into<PathBuf>) -> Self {
+        self.template = Some(path.into());
+        self
+    }
+
+    /// Shorthand for `format(OutputFormat::Markdown)` / `format(OutputFormat::Plain)`.
+    pub fn markdown(mut self, enabled: bool) -> Self {
+        self.format = if enabled { OutputFormat::Markdown } else { OutputFormat::Plain };
+        self
+    }
+
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// `max_size` in bytes.
+    pub(crate) fn max_size_bytes(&self) -> u64 {
+        (self.max_size * 1024.0 * 1024.0) as u64
+    }
+
+    /// The tokenizer in effect: `tokenizer` if set, `cl100k_base` if `gpt4_tokens` is set,
+    /// `p50k_base` under a `max_tokens` budget, which a word count would underestimate,
+    /// and a whitespace word count otherwise.
+    pub fn resolved_tokenizer(&self) -> Tokenizer {
+        match self.tokenizer {
+            Some(tokenizer) => tokenizer,
+            None if self.gpt4_tokens => Tokenizer::Cl100kBase,
+            None if self.max_tokens.is_some() => Tokenizer::P50kBase,
+            None => Tokenizer::Whitespace,
+        }
+    }
+
+    pub fn gpt4_tokens(mut self, enabled: bool) -> Self {
+        self.gpt4_tokens = enabled;
+        self
+    }
+
+    pub fn max_tokens(mut self, budget: usize) -> Self {
+        self.max_tokens = Some(budget);
+        self
+    }
+
+    pub fn include_git_changes(mut self, enabled: bool) -> Self {
+        self.include_git_changes = enabled;
+        self
+    }
+
+    pub fn no_staged_diff(mut self, enabled: bool) -> Self {
+        self.no_staged_diff = enabled;
+        self
+    }
+
+    pub fn no_unstaged_diff(mut self, enabled: bool) -> Self {
+        self.no_unstaged_diff = enabled;
+        self
+    }
+
+    pub fn git_log(mut self, count: usize) -> Self {
+        self.git_log = Some(count);
+        self
+    }
+
+    pub fn git_log_stat(mut self, enabled: bool) -> Self {
+        self.git_log_stat = enabled;
+        self
+    }
+
+    pub fn git_diff_base(mut self, base: impl Into<String>) -> Self {
+        self.git_diff_base = Some(base.into());
+        self
+    }
+
+    pub fn changed_since(mut self, reference: impl Into<String>) -> Self {
+        self.changed_since = Some(reference.into());
+        self
+    }
+
+    pub fn changed_only(mut self, enabled: bool) -> Self {
+        self.changed_only = enabled;
+        self
+    }
+
+    pub fn changed_neighbors(mut self, enabled: bool) -> Self {
+        self.changed_neighbors = enabled;
+        self
+    }
+
+    pub fn verbose(mut self, enabled: bool) -> Self {
+        self.verbose = enabled;
+        self
+    }
+
+    pub fn include_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.include_dirs = Some(paths(dirs));
+        self
+    }
+
+    pub fn exclude_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.exclude_dirs = Some(paths(dirs));
+        self
+    }
+
+    pub fn exclude_node_modules(mut self, enabled: bool) -> Self {
+        self.exclude_node_modules = enabled;
+        self
+    }
+
+    pub fn exclude_build_dirs(mut self, enabled: bool) -> Self {
+        self.exclude_build_dirs = enabled;
+        self
+    }
+
+    pub fn exclude_hidden_dirs(mut self, enabled: bool) -> Self {
+        self.exclude_hidden_dirs = enabled;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn exclude_patterns<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_patterns = Some(strings(patterns));
+        self
+    }
+
+    pub fn include_patterns<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_patterns = Some(strings(patterns));
+        self
+    }
+
+    pub fn pattern_target(mut self, target: PatternTarget) -> Self {
+        self.pattern_target = Some(target);
+        self
+    }
+
+    pub fn exclude_globs<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_globs = Some(strings(globs));
+        self
+    }
+
+    pub fn include_globs<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_globs = Some(strings(globs));
+        self
+    }
+
+    pub fn parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    /// Order of files in the output; identical for sequential and parallel runs.
+    pub fn sort(mut self, order: SortOrder) -> Self {
+        self.sort = order;
+        self
+    }
+
+    pub fn tree(mut self, enabled: bool) -> Self {
+        self.tree = enabled;
+        self
+    }
+
+    pub fn tree_excluded(mut self, enabled: bool) -> Self {
+        self.tree_excluded = enabled;
+        self
+    }
+
+    pub fn strip_comments(mut self, enabled: bool) -> Self {
+        self.strip_comments = enabled;
+        self
+    }
+
+    pub fn strip_docstrings(mut self, enabled: bool) -> Self {
+        self.strip_docstrings = enabled;
+        self
+    }
+
+    pub fn outline(mut self, enabled: bool) -> Self {
+        self.outline = enabled;
+        self
+    }
+
+    pub fn outline_globs<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.outline_globs = Some(strings(globs));
+        self
+    }
+
+    pub fn full_globs<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.full_globs = Some(strings(globs));
+        self
+    }
+
+    pub fn truncate(mut self, truncation: Truncation) -> Self {
+        self.truncate = Some(truncation);
+        self
+    }
+
+    pub fn truncate_globs<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.truncate_globs = Some(strings(globs));
+        self
+    }
+
+    pub fn truncate_rules<I>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = TruncateRule>,
+    {
+        self.truncate_rules = Some(rules.into_iter().collect());
+        self
+    }
+
+    pub fn normalize(mut self, normalize: Normalization) -> Self {
+        self.normalize = Some(normalize);
+        self
+    }
+
+    pub fn encoding_fallback(mut self, policy: EncodingPolicy) -> Self {
+        self.encoding_fallback = policy;
+        self
+    }
+
+    pub fn list_skipped(mut self, enabled: bool) -> Self {
+        self.list_skipped = enabled;
+        self
+    }
+
+    pub fn redact_secrets(mut self, enabled: bool) -> Self {
+        self.redact_secrets = enabled;
+        self
+    }
+
+    pub fn fail_on_secrets(mut self, enabled: bool) -> Self {
+        self.fail_on_secrets = enabled;
+        self
+    }
+
+    pub fn redact_rules<I>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = RedactRule>,
+    {
+        self.redact_rules = rules.into_iter().collect();
+        self
+    }
+
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled;
+        self
+    }
+
+    pub fn wp_exclude_plugins<I, S>(mut self, plugins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.wp_exclude_plugins = Some(strings(plugins));
+        self
+    }
+
+    pub fn wp_include_only_plugins<I, S>(mut self, plugins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.wp_include_only_plugins = Some(strings(plugins));
+        self
+    }
+
+    pub fn wp_include_theme(mut self, theme: impl Into<String>) -> Self {
+        self.wp_include_theme = Some(theme.into());
+        self
+    }
+
+    /// Checks the options for conflicting or out-of-range settings.
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(include_dirs), Some(exclude_dirs)) = (&self.include_dirs, &self.exclude_dirs) {
+            for include_dir in include_dirs {
+                for exclude_dir in exclude_dirs {
+                    if exclude_dir.starts_with(include_dir) {
+                        return Err(anyhow::anyhow!(
+                            "Conflict: exclude directory '{}' is within include directory '{}'",
+                            exclude_dir.display(),
+                            include_dir.display()
+                        ));
+                    }
+                }
+            }
+        }
+
+        if self.max_size > 100.0 {
+            return Err(anyhow::anyhow!("Max file size cannot exceed 100MB"));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves `self.profile` through the manager and merges it into these options.
+    pub fn resolve_profile(&mut self, profile_manager: &ProfileManager) {
+        let Some(profile_name) = self.profile.clone() else {
+            return;
+        };
+
+        let profile = if profile_name == "wordpress" {
+            // Special handling for WordPress to enable path-aware resolution
+            let default_path = PathBuf::from(".");
+            let path = self.target_dirs.first().unwrap_or(&default_path);
+            profile_manager.resolve_wordpress_path_aware(&profile_name, path, self)
+        } else {
+            profile_manager.resolve(&profile_name)
+        };
+
+        if let Some(p) = profile {
+            if self.verbose {
+                info!("Applied profile: {}", p.description);
+            }
+            self.apply_profile(p);
+        } else {
+            warn!("Profile '{}' not found. Using provided arguments only.", profile_name);
+        }
+    }
+
+    /// Merges profile settings into options that have not been set explicitly.
+    pub fn apply_profile(&mut self, p: Profile) {
+        if self.extensions.is_none() {
+            self.extensions = Some(p.allowed_extensions);
+        }
+        if self.allowed_filenames.is_none() {
+            self.allowed_filenames = Some(p.allowed_filenames);
+        }
+        // Append globs from profile to any existing options
+        if !p.include_globs.is_empty() {
+            let mut current_globs = self.include_globs.clone().unwrap_or_default();
+            for g in p.include_globs {
+                if !current_globs.contains(&g) {
+                    current_globs.push(g);
+                }
+            }
+            self.include_globs = Some(current_globs);
+        }
+        for rule in p.redact {
+            if !self.redact_rules.contains(&rule) {
+                self.redact_rules.push(rule);
+            }
+        }
+
+        if self.format == OutputFormat::Plain && p.markdown == Some(true) {
+            self.format = OutputFormat::Markdown;
+        }
+
+        if self.max_size == 0.0
+            && let Some(max_size) = p.max_size
+        {
+            self.max_size = max_size;
+        }
+        // An explicit --gpt4-tokens still wins over a profile's tokenizer
+        if self.tokenizer.is_none() && !self.gpt4_tokens {
+            self.tokenizer = p.tokenizer;
+        }
+        if !self.gpt4_tokens
+            && let Some(gpt4_tokens) = p.gpt4_tokens
+        {
+            self.gpt4_tokens = gpt4_tokens;
+        }
+        if !self.include_git_changes
+            && let Some(include_git_changes) = p.include_git_changes
+        {
+            self.include_git_changes = include_git_changes;
+        }
+        if !self.no_staged_diff
+            && let Some(no_staged_diff) = p.no_staged_diff
+        {
+            self.no_staged_diff = no_staged_diff;
+        }
+        if !self.no_unstaged_diff
+            && let Some(no_unstaged_diff) = p.no_unstaged_diff
+        {
+            self.no_unstaged_diff = no_unstaged_diff;
+        }
+        if self.git_log.is_none() {
+            self.git_log = p.git_log;
+        }
+        if !self.git_log_stat
+            && let Some(git_log_stat) = p.git_log_stat
+        {
+            self.git_log_stat = git_log_stat;
+        }
+        if self.git_diff_base.is_none() {
+            self.git_diff_base = p.git_diff_base;
+        }
+        if self.include_dirs.is_none() {
+            self.include_dirs = p.include_dirs;
+        }
+        if self.exclude_dirs.is_none() {
+            self.exclude_dirs = p.exclude_dirs;
+        }
+        if self.exclude_patterns.is_none() {
+            self.exclude_patterns = p.exclude_patterns;
+        }
+        if self.include_patterns.is_none() {
+            self.include_patterns = p.include_patterns;
+        }
+        if self.pattern_target.is_none() {
+            self.pattern_target = p.pattern_target;
+        }
+        if !self.strip_comments
+            && let Some(strip_comments) = p.strip_comments
+        {
+            self.strip_comments = strip_comments;
+        }
+        if !self.strip_docstrings
+            && let Some(strip_docstrings) = p.strip_docstrings
+        {
+            self.strip_docstrings = strip_docstrings;
+        }
+        if !self.outline
+            && let Some(outline) = p.outline
+        {
+            self.outline = outline;
+        }
+        if self.outline_globs.is_none() {
+            self.outline_globs = p.outline_globs;
+        }
+        if self.full_globs.is_none() {
+            self.full_globs = p.full_globs;
+        }
+        if self.truncate.is_none() {
+            self.truncate = p.truncate;
+        }
+        if self.truncate_globs.is_none() {
+            self.truncate_globs = p.truncate_globs;
+        }
+        if self.truncate_rules.is_none() {
+            self.truncate_rules = p.truncate_rules;
+        }
+        if self.normalize.is_none() {
+            self.normalize = p.normalize;
+        }
+        if self.exclude_globs.is_none() {
+            self.exclude_globs = p.exclude_globs;
+        }
+        if !self.exclude_node_modules
+            && let Some(exclude_node_modules) = p.exclude_node_modules
+        {
+            self.exclude_node_modules = exclude_node_modules;
+        }
+        if !self.exclude_build_dirs
+            && let Some(exclude_build_dirs) = p.exclude_build_dirs
+        {
+            self.exclude_build_dirs = exclude_build_dirs;
+        }
+        if !self.exclude_hidden_dirs
+            && let Some(exclude_hidden_dirs) = p.exclude_hidden_dirs
+        {
+            self.exclude_hidden_dirs = exclude_hidden_dirs;
+        }
+        if self.max_depth == 0
+            && let Some(max_depth) = p.max_depth
+        {
+            self.max_depth = max_depth;
+        }
+    }
+}
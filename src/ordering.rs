@@ -0,0 +1,116 @@
+use crate::git::{file_churn, find_git_root};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+use tracing::warn;
+
+/// Order in which files appear in the output. The order is the same whether files are
+/// read sequentially or in parallel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// Directory walk order (entries of each directory sorted by name).
+    #[default]
+    Walk,
+    /// Full path, lexicographically.
+    Path,
+    /// File size, smallest first.
+    Size,
+    /// Modification time, most recently modified first.
+    Mtime,
+    /// Number of commits touching the file, most frequently changed first.
+    #[serde(alias = "git-churn")]
+    Churn,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "walk" => Ok(SortOrder::Walk),
+            "path" => Ok(SortOrder::Path),
+            "size" => Ok(SortOrder::Size),
+            "mtime" => Ok(SortOrder::Mtime),
+            "churn" | "git-churn" => Ok(SortOrder::Churn),
+            other => Err(format!(
+                "unknown sort order '{}' (expected walk, path, size, mtime or churn)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SortOrder::Walk => "walk",
+            SortOrder::Path => "path",
+            SortOrder::Size => "size",
+            SortOrder::Mtime => "mtime",
+            SortOrder::Churn => "churn",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sorts `items` by `order`, using `path_of` to get each item's path. Ties are broken by
+/// path so the result never depends on walk or thread timing. `Walk` keeps the input order.
+pub(crate) fn sort_by_order<T>(items: &mut [T], order: SortOrder, path_of: impl Fn(&T) -> &Path) {
+    match order {
+        SortOrder::Walk => {}
+        SortOrder::Path => items.sort_by(|a, b| path_of(a).cmp(path_of(b))),
+        SortOrder::Size => {
+            let sizes: HashMap<PathBuf, u64> = items
+                .iter()
+                .map(|item| {
+                    let path = path_of(item);
+                    (path.to_path_buf(), fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+                })
+                .collect();
+            items.sort_by(|a, b| {
+                let (a, b) = (path_of(a), path_of(b));
+                sizes[a].cmp(&sizes[b]).then_with(|| a.cmp(b))
+            });
+        }
+        SortOrder::Mtime => {
+            let mtimes: HashMap<PathBuf, SystemTime> = items
+                .iter()
+                .map(|item| {
+                    let path = path_of(item);
+                    let mtime = fs::metadata(path)
+                        .and_then(|m| m.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+                    (path.to_path_buf(), mtime)
+                })
+                .collect();
+            items.sort_by(|a, b| {
+                let (a, b) = (path_of(a), path_of(b));
+                Reverse(mtimes[a]).cmp(&Reverse(mtimes[b])).then_with(|| a.cmp(b))
+            });
+        }
+        SortOrder::Churn => {
+            let churn = items
+                .first()
+                .and_then(|item| find_git_root(path_of(item)).ok().flatten())
+                .and_then(|root| match file_churn(&root) {
+                    Ok(churn) => Some(churn),
+                    Err(e) => {
+                        warn!("Failed to read git history for churn ordering: {}", e);
+                        None
+                    }
+                })
+                .unwrap_or_default();
+            let count = |path: &Path| churn.get(path).copied().unwrap_or(0);
+            items.sort_by(|a, b| {
+                let (a, b) = (path_of(a), path_of(b));
+                Reverse(count(a)).cmp(&Reverse(count(b))).then_with(|| a.cmp(b))
+            });
+        }
+    }
+}
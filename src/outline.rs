@@ -0,0 +1,313 @@
+use crate::comments::literal_spans;
+use crate::filters::match_glob;
+use crate::language::language_for_path;
+use crate::options::FlattenOptions;
+use std::ops::Range;
+use std::path::Path;
+
+/// Placeholder for an elided function body.
+const ELIDED: &str = "{ ... }";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Rust,
+    /// JavaScript, TypeScript and PHP.
+    Script,
+    Python,
+}
+
+fn family_for(path: &Path) -> Option<Family> {
+    Some(match language_for_path(path)? {
+        "rust" => Family::Rust,
+        "javascript" | "jsx" | "typescript" | "tsx" | "php" => Family::Script,
+        "python" => Family::Python,
+        _ => return None,
+    })
+}
+
+/// Whether the file at `relative_path` is reduced to an outline: `full_globs` win over
+/// `outline` and `outline_globs`, and only supported languages are outlined.
+pub(crate) fn selects(options: &FlattenOptions, relative_path: &Path) -> bool {
+    let matches = |globs: &Option<Vec<String>>| {
+        globs.iter().flatten().any(|glob| match_glob(glob, relative_path))
+    };
+    family_for(relative_path).is_some()
+        && !matches(&options.full_globs)
+        && (options.outline || matches(&options.outline_globs))
+}
+
+/// Reduces `content` to its structural skeleton: declarations, type, trait and class
+/// bodies and function signatures are kept, function and method bodies are replaced by
+/// `{ ... }` (`...` in Python, after the docstring). Comments outside bodies are kept.
+/// Content in an unsupported language is returned as is.
+pub(crate) fn outline(path: &Path, content: &str) -> String {
+    let (Some(family), Some(literals)) = (family_for(path), literal_spans(path, content)) else {
+        return content.to_string();
+    };
+    let code = Code { src: content, literals };
+    match family {
+        Family::Python => outline_python(&code),
+        family => outline_braces(&code, family),
+    }
+}
+
+/// Source text with the ranges taken by comments and literals.
+struct Code<'a> {
+    src: &'a str,
+    literals: Vec<Range<usize>>,
+}
+
+impl Code<'_> {
+    /// Positions and bytes outside comments and literals, starting at `from`.
+    fn bytes_from(&self, from: usize) -> impl Iterator<Item = (usize, u8)> + '_ {
+        let first = self.literals.partition_point(|l| l.end <= from);
+        let mut literals = self.literals[first..].iter().peekable();
+        let mut i = from;
+        std::iter::from_fn(move || {
+            while let Some(literal) = literals.peek() {
+                if i < literal.start {
+                    break;
+                }
+                i = i.max(literal.end);
+                literals.next();
+            }
+            let byte = *self.src.as_bytes().get(i)?;
+            i += 1;
+            Some((i - 1, byte))
+        })
+    }
+
+    /// `src[range]` without its comments and literals, which are replaced by a space.
+    fn code_text(&self, range: Range<usize>) -> String {
+        let mut text = String::new();
+        let mut last = range.start;
+        for (i, _) in self.bytes_from(range.start).take_while(|&(i, _)| i < range.end) {
+            if i > last {
+                text.push(' ');
+            }
+            last = i + 1;
+            // Code bytes may be parts of a multi-byte character
+            if let Some(c) = self.src.get(i..).and_then(|s| s.chars().next()) {
+                text.push(c);
+            }
+        }
+        text
+    }
+
+    /// Whether `i` lies inside a comment or literal.
+    fn in_literal(&self, i: usize) -> bool {
+        let index = self.literals.partition_point(|l| l.end <= i);
+        self.literals.get(index).is_some_and(|l| l.start <= i)
+    }
+
+    /// Position of the `}` closing the `{` at `open`, or the end of the source.
+    fn matching_brace(&self, open: usize) -> usize {
+        let mut depth = 0usize;
+        for (i, byte) in self.bytes_from(open) {
+            match byte {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.src.len()
+    }
+}
+
+fn has_word(text: &str, word: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$').any(|w| w == word)
+}
+
+/// Whether the block opened after `header` (the code since the previous `{`, `}` or `;`)
+/// is a function body.
+fn is_function_body(header: &str, family: Family) -> bool {
+    let header = header.trim();
+    match family {
+        Family::Rust => has_word(header, "fn"),
+        Family::Script => {
+            if ["class", "interface", "enum", "namespace", "module", "trait"].iter().any(|k| has_word(header, k)) {
+                return false;
+            }
+            // `f(): { a: number } {` and `f(): Promise<{ a: number }> {` open a type first
+            let generics = header.matches('<').count() > header.matches('>').count() - header.matches("=>").count();
+            if header.ends_with(':') || generics {
+                return false;
+            }
+            if has_word(header, "function") || header.ends_with("=>") {
+                return true;
+            }
+            if matches!(header, "else" | "try" | "finally" | "do") {
+                return true;
+            }
+            // Methods, getters and control statements: `name(args)` or `name(args): Type`
+            header.rfind(')').is_some_and(|close| {
+                let after = header[close + 1..].trim_start();
+                after.is_empty() || after.starts_with(':')
+            })
+        }
+        Family::Python => false,
+    }
+}
+
+fn outline_braces(code: &Code, family: Family) -> String {
+    let src = code.src;
+    let mut out = String::with_capacity(src.len() / 2);
+    let mut copied = 0;
+    let mut header_start = 0;
+    let mut skip_to = 0;
+    for (i, byte) in code.bytes_from(0) {
+        if i < skip_to {
+            continue;
+        }
+        match byte {
+            b';' | b'}' => header_start = i + 1,
+            b'{' => {
+                if is_function_body(&code.code_text(header_start..i), family) {
+                    let close = code.matching_brace(i);
+                    out.push_str(&src[copied..i]);
+                    out.push_str(ELIDED);
+                    copied = (close + 1).min(src.len());
+                    skip_to = copied;
+                }
+                header_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&src[copied..]);
+    out
+}
+
+/// One logical Python line: physical lines joined by open brackets, backslashes or
+/// multi-line strings.
+struct Statement<'a> {
+    text: &'a str,
+    indent: usize,
+    /// Code of the statement, without comments and literals.
+    code: String,
+}
+
+fn python_statements<'a>(code: &Code<'a>) -> Vec<Statement<'a>> {
+    let src = code.src;
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut depth = 0i32;
+    let line_end = |end: usize, statements: &mut Vec<Statement<'a>>, start: &mut usize| {
+        let text = &src[*start..end];
+        let indent = text.len() - text.trim_start_matches([' ', '\t']).len();
+        statements.push(Statement { text, indent, code: code.code_text(*start..end) });
+        *start = end;
+    };
+    for (i, byte) in code.bytes_from(0) {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'\n' => {
+                let before = src[..i].trim_end_matches('\r');
+                let continued = before.ends_with('\\') && !code.in_literal(before.len() - 1);
+                if depth <= 0 && !continued {
+                    depth = 0;
+                    line_end(i + 1, &mut statements, &mut start);
+                }
+            }
+            _ => {}
+        }
+    }
+    if start < src.len() {
+        line_end(src.len(), &mut statements, &mut start);
+    }
+    statements
+}
+
+fn is_def(code: &str) -> bool {
+    let code = code.trim_start();
+    let code = code.strip_prefix("async").map_or(code, str::trim_start);
+    code.starts_with("def ") || code.starts_with("def\t")
+}
+
+/// A `def` header whose body starts on the next line (not `def f(): return 1`).
+fn opens_body(code: &str) -> bool {
+    code.trim_end().ends_with(':')
+}
+
+fn is_docstring(statement: &Statement) -> bool {
+    statement.code.trim().is_empty() && {
+        let text = statement.text.trim_start();
+        let text = text.trim_start_matches(['r', 'R', 'u', 'U', 'b', 'B']);
+        text.starts_with('"') || text.starts_with('\'')
+    }
+}
+
+/// Progress through the body of a `def` being elided.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Body {
+    /// Nothing seen yet; a string here is the docstring.
+    Start,
+    /// The docstring has been copied.
+    AfterDocstring,
+    /// `...` has been written.
+    Elided,
+}
+
+fn outline_python(code: &Code) -> String {
+    let mut out = String::with_capacity(code.src.len() / 2);
+    // Indentation of the `def` whose body is being elided
+    let mut def: Option<(usize, Body)> = None;
+    // Blank and comment lines seen in a body, kept if the body turns out to have ended
+    let mut pending: Vec<Statement> = Vec::new();
+    for statement in python_statements(code) {
+        let blank = statement.code.trim().is_empty() && !is_docstring(&statement);
+        if let Some((indent, body)) = def {
+            if blank {
+                pending.push(statement);
+                continue;
+            }
+            if statement.indent > indent {
+                pending.clear();
+                let body = match body {
+                    Body::Start if is_docstring(&statement) => {
+                        out.push_str(statement.text);
+                        Body::AfterDocstring
+                    }
+                    Body::Start | Body::AfterDocstring => {
+                        push_ellipsis(&mut out, &statement);
+                        Body::Elided
+                    }
+                    Body::Elided => Body::Elided,
+                };
+                def = Some((indent, body));
+                continue;
+            }
+            for line in pending.drain(..) {
+                if line.text.trim().is_empty() || line.indent <= indent {
+                    out.push_str(line.text);
+                }
+            }
+            def = None;
+        }
+        out.push_str(statement.text);
+        if is_def(&statement.code) && opens_body(&statement.code) {
+            def = Some((statement.indent, Body::Start));
+        }
+    }
+    for line in pending {
+        if line.text.trim().is_empty() {
+            out.push_str(line.text);
+        }
+    }
+    out
+}
+
+/// Writes `...` at the indentation of `statement`, the first elided one of a body.
+fn push_ellipsis(out: &mut String, statement: &Statement) {
+    let indent = &statement.text[..statement.indent];
+    let ending = if statement.text.ends_with("\r\n") { "\r\n" } else { "\n" };
+    out.push_str(indent);
+    out.push_str("...");
+    out.push_str(ending);
+}
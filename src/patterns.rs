@@ -0,0 +1,97 @@
+use crate::filters::SkipReason;
+use crate::options::FlattenOptions;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What `include_patterns` / `exclude_patterns` are matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternTarget {
+    /// The path relative to the target directory, with `/` separators.
+    #[default]
+    Path,
+    /// The file content (read once more for every candidate, so slower).
+    Content,
+}
+
+impl FromStr for PatternTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "path" => Ok(PatternTarget::Path),
+            "content" => Ok(PatternTarget::Content),
+            other => Err(format!("unknown pattern target '{}' (expected path or content)", other)),
+        }
+    }
+}
+
+impl fmt::Display for PatternTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PatternTarget::Path => "path",
+            PatternTarget::Content => "content",
+        })
+    }
+}
+
+fn compile(patterns: Option<&Vec<String>>, kind: &str) -> Result<Vec<Regex>> {
+    patterns
+        .into_iter()
+        .flatten()
+        .map(|p| Regex::new(p).with_context(|| format!("Invalid {} pattern '{}'", kind, p)))
+        .collect()
+}
+
+/// Compiled `include_patterns` / `exclude_patterns`.
+///
+/// A file is excluded if any exclude pattern matches; if include patterns are given, it
+/// must also match at least one of them. Patterns are unanchored regular expressions, so
+/// `^` and `$` are needed to match a whole path.
+pub(crate) struct PatternFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    target: PatternTarget,
+}
+
+impl PatternFilter {
+    pub(crate) fn new(options: &FlattenOptions) -> Result<Self> {
+        Ok(Self {
+            include: compile(options.include_patterns.as_ref(), "include")?,
+            exclude: compile(options.exclude_patterns.as_ref(), "exclude")?,
+            target: options.pattern_target.unwrap_or_default(),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Returns why the patterns exclude the file, or `None` if they let it through.
+    pub(crate) fn exclusion(&self, path: &Path, relative_path: &Path) -> Option<SkipReason> {
+        if self.is_empty() {
+            return None;
+        }
+        let text = match self.target {
+            PatternTarget::Path => relative_path.to_string_lossy().replace('\\', "/"),
+            PatternTarget::Content => match fs::read(path) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                // Unreadable files are reported when they are processed
+                Err(_) => return None,
+            },
+        };
+        if let Some(re) = self.exclude.iter().find(|re| re.is_match(&text)) {
+            return Some(SkipReason::ExcludePattern(re.as_str().to_string()));
+        }
+        if self.include.is_empty() || self.include.iter().any(|re| re.is_match(&text)) {
+            None
+        } else {
+            Some(SkipReason::NoIncludePatternMatch)
+        }
+    }
+}
@@ -1,4 +1,9 @@
 use crate::config::CustomProfile;
+use crate::normalize::Normalization;
+use crate::patterns::PatternTarget;
+use crate::redact::RedactRule;
+use crate::tokens::Tokenizer;
+use crate::truncate::{TruncateRule, Truncation};
 use crate::wordpress_profile::WordPressProfilePlugin;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
@@ -12,16 +17,32 @@ pub struct Profile {
     pub allowed_extensions: Vec<String>,
     pub allowed_filenames: Vec<String>,
     pub include_globs: Vec<String>,
+    /// `[[redact]]` rules, added to those of the parent profile.
+    pub redact: Vec<RedactRule>,
     pub markdown: Option<bool>,
     pub max_size: Option<f64>,
+    pub tokenizer: Option<Tokenizer>,
     pub gpt4_tokens: Option<bool>,
     pub include_git_changes: Option<bool>,
     pub no_staged_diff: Option<bool>,
     pub no_unstaged_diff: Option<bool>,
+    pub git_log: Option<usize>,
+    pub git_log_stat: Option<bool>,
+    pub git_diff_base: Option<String>,
     pub include_dirs: Option<Vec<PathBuf>>,
     pub exclude_dirs: Option<Vec<PathBuf>>,
     pub exclude_patterns: Option<Vec<String>>,
     pub include_patterns: Option<Vec<String>>,
+    pub pattern_target: Option<PatternTarget>,
+    pub normalize: Option<Normalization>,
+    pub strip_comments: Option<bool>,
+    pub strip_docstrings: Option<bool>,
+    pub outline: Option<bool>,
+    pub outline_globs: Option<Vec<String>>,
+    pub full_globs: Option<Vec<String>>,
+    pub truncate: Option<Truncation>,
+    pub truncate_globs: Option<Vec<String>>,
+    pub truncate_rules: Option<Vec<TruncateRule>>,
     pub exclude_globs: Option<Vec<String>>,
     pub exclude_node_modules: Option<bool>,
     pub exclude_build_dirs: Option<bool>,
@@ -40,16 +61,31 @@ impl Profile {
             allowed_extensions,
             allowed_filenames,
             include_globs: Vec::new(),
+            redact: Vec::new(),
             markdown: None,
             max_size: None,
+            tokenizer: None,
             gpt4_tokens: None,
             include_git_changes: None,
             no_staged_diff: None,
             no_unstaged_diff: None,
+            git_log: None,
+            git_log_stat: None,
+            git_diff_base: None,
             include_dirs: None,
             exclude_dirs: None,
             exclude_patterns: None,
             include_patterns: None,
+            pattern_target: None,
+            normalize: None,
+            strip_comments: None,
+            strip_docstrings: None,
+            outline: None,
+            outline_globs: None,
+            full_globs: None,
+            truncate: None,
+            truncate_globs: None,
+            truncate_rules: None,
             exclude_globs: None,
             exclude_node_modules: None,
             exclude_build_dirs: None,
@@ -83,21 +119,43 @@ impl Profile {
             }
         }
 
+        let mut merged_redact = self.redact.clone();
+        for rule in &child.redact {
+            if !merged_redact.contains(rule) {
+                merged_redact.push(rule.clone());
+            }
+        }
+
         Profile {
             description: child.description.clone(),
             allowed_extensions: merged_extensions,
             allowed_filenames: merged_filenames,
             include_globs: merged_globs,
+            redact: merged_redact,
             markdown: child.markdown.or(self.markdown),
             max_size: child.max_size.or(self.max_size),
+            tokenizer: child.tokenizer.or(self.tokenizer),
             gpt4_tokens: child.gpt4_tokens.or(self.gpt4_tokens),
             include_git_changes: child.include_git_changes.or(self.include_git_changes),
             no_staged_diff: child.no_staged_diff.or(self.no_staged_diff),
             no_unstaged_diff: child.no_unstaged_diff.or(self.no_unstaged_diff),
+            git_log: child.git_log.or(self.git_log),
+            git_log_stat: child.git_log_stat.or(self.git_log_stat),
+            git_diff_base: child.git_diff_base.clone().or(self.git_diff_base.clone()),
             include_dirs: child.include_dirs.clone().or(self.include_dirs.clone()),
             exclude_dirs: child.exclude_dirs.clone().or(self.exclude_dirs.clone()),
             exclude_patterns: child.exclude_patterns.clone().or(self.exclude_patterns.clone()),
             include_patterns: child.include_patterns.clone().or(self.include_patterns.clone()),
+            pattern_target: child.pattern_target.or(self.pattern_target),
+            normalize: child.normalize.or(self.normalize),
+            strip_comments: child.strip_comments.or(self.strip_comments),
+            strip_docstrings: child.strip_docstrings.or(self.strip_docstrings),
+            outline: child.outline.or(self.outline),
+            outline_globs: child.outline_globs.clone().or(self.outline_globs.clone()),
+            full_globs: child.full_globs.clone().or(self.full_globs.clone()),
+            truncate: child.truncate.or(self.truncate),
+            truncate_globs: child.truncate_globs.clone().or(self.truncate_globs.clone()),
+            truncate_rules: child.truncate_rules.clone().or(self.truncate_rules.clone()),
             exclude_globs: child.exclude_globs.clone().or(self.exclude_globs.clone()),
             exclude_node_modules: child.exclude_node_modules.or(self.exclude_node_modules),
             exclude_build_dirs: child.exclude_build_dirs.or(self.exclude_build_dirs),
@@ -185,16 +243,31 @@ impl ProfileManager {
             custom.allowed_filenames.clone().unwrap_or_default(),
         );
         child.include_globs = custom.include_globs.clone().unwrap_or_default();
+        child.redact = custom.redact.clone().unwrap_or_default();
         child.markdown = custom.markdown;
         child.max_size = custom.max_size;
+        child.tokenizer = custom.tokenizer;
         child.gpt4_tokens = custom.gpt4_tokens;
         child.include_git_changes = custom.include_git_changes;
         child.no_staged_diff = custom.no_staged_diff;
         child.no_unstaged_diff = custom.no_unstaged_diff;
+        child.git_log = custom.git_log;
+        child.git_log_stat = custom.git_log_stat;
+        child.git_diff_base = custom.git_diff_base.clone();
         child.include_dirs = custom.include_dirs.clone();
         child.exclude_dirs = custom.exclude_dirs.clone();
         child.exclude_patterns = custom.exclude_patterns.clone();
         child.include_patterns = custom.include_patterns.clone();
+        child.pattern_target = custom.pattern_target;
+        child.normalize = custom.normalize;
+        child.strip_comments = custom.strip_comments;
+        child.strip_docstrings = custom.strip_docstrings;
+        child.outline = custom.outline;
+        child.outline_globs = custom.outline_globs.clone();
+        child.full_globs = custom.full_globs.clone();
+        child.truncate = custom.truncate;
+        child.truncate_globs = custom.truncate_globs.clone();
+        child.truncate_rules = custom.truncate_rules.clone();
         child.exclude_globs = custom.exclude_globs.clone();
         child.exclude_node_modules = custom.exclude_node_modules;
         child.exclude_build_dirs = custom.exclude_build_dirs;
@@ -227,14 +300,14 @@ impl ProfileManager {
         &self, 
         name: &str, 
         path: &std::path::Path,
-        args: &crate::Args
+        options: &crate::FlattenOptions
     ) -> Option<Profile> {
          self.wordpress.get_profile_for_path(
             name,
             path,
-            args.wp_exclude_plugins.as_deref(),
-            args.wp_include_only_plugins.as_deref(),
-            args.wp_include_theme.as_deref(),
+            options.wp_exclude_plugins.as_deref(),
+            options.wp_include_only_plugins.as_deref(),
+            options.wp_include_theme.as_deref(),
         )
     }
 }
@@ -257,16 +330,31 @@ static BUILT_IN_PROFILES: Lazy<HashMap<&'static str, Profile>> = Lazy::new(|| {
                 "postcss.config.js".to_string(), "middleware.ts".to_string(), "schema.prisma".to_string(),
             ],
             include_globs: Vec::new(),
+            redact: Vec::new(),
             markdown: None,
             max_size: None,
+            tokenizer: None,
             gpt4_tokens: None,
             include_git_changes: None,
             no_staged_diff: None,
             no_unstaged_diff: None,
+            git_log: None,
+            git_log_stat: None,
+            git_diff_base: None,
             include_dirs: None,
             exclude_dirs: None,
             exclude_patterns: None,
             include_patterns: None,
+            pattern_target: None,
+            normalize: None,
+            strip_comments: None,
+            strip_docstrings: None,
+            outline: None,
+            outline_globs: None,
+            full_globs: None,
+            truncate: None,
+            truncate_globs: None,
+            truncate_rules: None,
             exclude_globs: None,
             exclude_node_modules: None,
             exclude_build_dirs: None,
@@ -284,16 +372,31 @@ static BUILT_IN_PROFILES: Lazy<HashMap<&'static str, Profile>> = Lazy::new(|| {
             ],
             allowed_filenames: vec!["CMakeLists.txt".to_string()],
             include_globs: Vec::new(),
+            redact: Vec::new(),
             markdown: None,
             max_size: None,
+            tokenizer: None,
             gpt4_tokens: None,
             include_git_changes: None,
             no_staged_diff: None,
             no_unstaged_diff: None,
+            git_log: None,
+            git_log_stat: None,
+            git_diff_base: None,
             include_dirs: None,
             exclude_dirs: None,
             exclude_patterns: None,
             include_patterns: None,
+            pattern_target: None,
+            normalize: None,
+            strip_comments: None,
+            strip_docstrings: None,
+            outline: None,
+            outline_globs: None,
+            full_globs: None,
+            truncate: None,
+            truncate_globs: None,
+            truncate_rules: None,
             exclude_globs: None,
             exclude_node_modules: None,
             exclude_build_dirs: None,
@@ -310,16 +413,31 @@ static BUILT_IN_PROFILES: Lazy<HashMap<&'static str, Profile>> = Lazy::new(|| {
             ],
             allowed_filenames: vec!["Cargo.toml".to_string(), "Cargo.lock".to_string()],
             include_globs: Vec::new(),
+            redact: Vec::new(),
             markdown: None,
             max_size: None,
+            tokenizer: None,
             gpt4_tokens: None,
             include_git_changes: None,
             no_staged_diff: None,
             no_unstaged_diff: None,
+            git_log: None,
+            git_log_stat: None,
+            git_diff_base: None,
             include_dirs: None,
             exclude_dirs: None,
             exclude_patterns: None,
             include_patterns: None,
+            pattern_target: None,
+            normalize: None,
+            strip_comments: None,
+            strip_docstrings: None,
+            outline: None,
+            outline_globs: None,
+            full_globs: None,
+            truncate: None,
+            truncate_globs: None,
+            truncate_rules: None,
             exclude_globs: None,
             exclude_node_modules: None,
             exclude_build_dirs: None,
@@ -0,0 +1,530 @@
+use crate::flattener::{FlattenedFile, OmittedFile, SkippedFile};
+use crate::git::{GitChanges, RepositoryInfo};
+use crate::tokens::Tokenizer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Layout of the flattened output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `# --- File: ... ---` markers followed by the raw content.
+    #[default]
+    Plain,
+    /// Every file wrapped in a fenced Markdown code block.
+    Markdown,
+    /// A single JSON document with a header, an array of files and the git section.
+    Json,
+    /// One JSON object per line, one line per file.
+    Jsonl,
+    /// `<document>` tags with `<source>` and `<document_content>`, as recommended for
+    /// LLM prompts, inside a `<repository>` element.
+    Xml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" | "text" | "txt" => Ok(OutputFormat::Plain),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            "xml" => Ok(OutputFormat::Xml),
+            other => Err(format!(
+                "unknown output format '{}' (expected plain, markdown, json, jsonl or xml)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Plain => "plain",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Xml => "xml",
+        };
+        f.write_str(name)
+    }
+}
+
+impl OutputFormat {
+    /// The built-in renderer for this format.
+    pub fn renderer(self) -> Box<dyn OutputRenderer> {
+        match self {
+            OutputFormat::Plain => Box::new(PlainRenderer),
+            OutputFormat::Markdown => Box::new(MarkdownRenderer),
+            OutputFormat::Json => Box::new(JsonRenderer),
+            OutputFormat::Jsonl => Box::new(JsonlRenderer),
+            OutputFormat::Xml => Box::new(XmlRenderer),
+        }
+    }
+}
+
+/// Run-level information available to the preamble.
+pub struct OutputHeader<'a> {
+    /// Canonicalized target directories.
+    pub roots: &'a [PathBuf],
+    pub profile: Option<&'a str>,
+    /// Repository the first target directory belongs to, when the renderer asked for it
+    /// via [`OutputRenderer::wants_repository_info`].
+    pub repository: Option<&'a RepositoryInfo>,
+    /// ASCII directory tree of the selected files, when requested.
+    pub tree: Option<&'a str>,
+}
+
+/// Totals available to the epilogue.
+pub struct OutputTotals<'a> {
+    pub file_count: usize,
+    /// Tokens written before the epilogue.
+    pub token_count: usize,
+    /// Tokenizer the counts were made with.
+    pub tokenizer: Tokenizer,
+    /// Files dropped to stay under the token budget, by path.
+    pub omitted: &'a [OmittedFile],
+    /// Files skipped for their size, as binary or as unreadable, when
+    /// `FlattenOptions::list_skipped` is set; empty otherwise.
+    pub skipped: &'a [SkippedFile],
+}
+
+/// Turns a flattening run into text. The flattener calls `preamble` once, `file` for
+/// every file in output order, `git_section` once after the last file (with `None` when
+/// git changes were not requested or not found) and finally `epilogue`.
+pub trait OutputRenderer {
+    fn preamble(&self, _header: &OutputHeader) -> String {
+        String::new()
+    }
+
+    /// Renders one file; `index` is the file's position in the output, starting at 0.
+    fn file(&self, file: &FlattenedFile, index: usize) -> String;
+
+    fn git_section(&self, _git: Option<&GitChanges>) -> String {
+        String::new()
+    }
+
+    fn epilogue(&self, _totals: &OutputTotals) -> String {
+        String::new()
+    }
+
+    /// Whether the preamble shows repository information (costs a couple of git calls).
+    fn wants_repository_info(&self) -> bool {
+        false
+    }
+}
+
+fn forward_slashes(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Suffix for the `# --- File: ... ---` marker of a file reduced to an outline.
+fn outline_marker(file: &FlattenedFile) -> &'static str {
+    if file.metadata.outline { " (outline)" } else { "" }
+}
+
+/// `# --- File: ... ---` markers followed by the raw content.
+pub struct PlainRenderer;
+
+impl OutputRenderer for PlainRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        header
+            .tree
+            .map(|tree| format!("# --- Directory Tree ---\n\n{}", tree))
+            .unwrap_or_default()
+    }
+
+    fn file(&self, file: &FlattenedFile, _index: usize) -> String {
+        format!(
+            "\n\n# --- File: {}{} ---\n\n{}",
+            file.metadata.path.to_string_lossy(),
+            outline_marker(file),
+            file.content
+        )
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        git.map(render_git_text).unwrap_or_default()
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        render_omitted_text(totals.omitted) + &render_skipped_text(totals.skipped)
+    }
+}
+
+/// Every file wrapped in a fenced Markdown code block.
+pub struct MarkdownRenderer;
+
+impl OutputRenderer for MarkdownRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        header
+            .tree
+            .map(|tree| format!("# --- Directory Tree ---\n```text\n{}```\n", tree))
+            .unwrap_or_default()
+    }
+
+    fn file(&self, file: &FlattenedFile, _index: usize) -> String {
+        format!(
+            "\n\n```{}\n# --- File: {}{} ---\n{}\n```\n",
+            file.metadata.extension,
+            file.metadata.path.to_string_lossy(),
+            outline_marker(file),
+            file.content
+        )
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        git.map(render_git_text).unwrap_or_default()
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        render_omitted_text(totals.omitted) + &render_skipped_text(totals.skipped)
+    }
+}
+
+fn render_omitted_text(omitted: &[OmittedFile]) -> String {
+    if omitted.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("\n\n# --- Omitted Files (token budget exceeded) ---\n");
+    for file in omitted {
+        output.push_str(&format!(
+            "# {} ({} tokens)\n",
+            forward_slashes(&file.relative_path),
+            file.token_count
+        ));
+    }
+    output
+}
+
+fn render_skipped_text(skipped: &[SkippedFile]) -> String {
+    if skipped.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("\n\n# --- Skipped Files (not in this output) ---\n");
+    for file in skipped {
+        let size = file.size.map(|s| format!(" ({} bytes)", s)).unwrap_or_default();
+        output.push_str(&format!(
+            "# {}{}: {}\n",
+            forward_slashes(&file.relative_path),
+            size,
+            file.reason
+        ));
+    }
+    output
+}
+
+fn skipped_json(file: &SkippedFile) -> serde_json::Value {
+    json!({
+        "path": forward_slashes(&file.relative_path),
+        "reason": file.reason.kind(),
+        "detail": file.reason.to_string(),
+        "size": file.size,
+    })
+}
+
+fn omitted_json(file: &OmittedFile) -> serde_json::Value {
+    json!({ "path": forward_slashes(&file.relative_path), "tokens": file.token_count })
+}
+
+fn render_git_text(git: &GitChanges) -> String {
+    let mut output = String::new();
+    output.push_str("\n\n# --- Git Changes ---\n");
+    output.push_str(&format!("# Repository: {}\n\n", git.repo_root.display()));
+
+    if let Some(status) = &git.status {
+        output.push_str("## Git Status:\n```bash\n");
+        output.push_str(status);
+        output.push_str("\n```\n\n");
+    }
+    if let Some(diff) = &git.staged_diff {
+        output.push_str("## Git Diff (Staged):\n```diff\n");
+        output.push_str(diff);
+        output.push_str("\n```\n\n");
+    }
+    if let Some(diff) = &git.unstaged_diff {
+        output.push_str("## Git Diff (Unstaged):\n```diff\n");
+        output.push_str(diff);
+        output.push_str("\n```\n\n");
+    }
+    if let Some(diff) = &git.base_diff {
+        let base = git.diff_base.as_deref().unwrap_or_default();
+        output.push_str(&format!("## Git Diff ({}...HEAD):\n```diff\n", base));
+        output.push_str(diff);
+        output.push_str("\n```\n\n");
+    }
+    if let Some(log) = &git.log {
+        output.push_str("## Git Log:\n```text\n");
+        output.push_str(log);
+        output.push_str("\n```\n\n");
+    }
+    output
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: String,
+    language: Option<&'a str>,
+    encoding: &'a str,
+    outline: bool,
+    truncated_lines: usize,
+    size: u64,
+    tokens: usize,
+    sha256: &'a str,
+    content: &'a str,
+}
+
+fn json_file(file: &FlattenedFile) -> JsonFile<'_> {
+    JsonFile {
+        path: forward_slashes(&file.metadata.relative_path),
+        language: file.metadata.language,
+        encoding: file.metadata.encoding,
+        outline: file.metadata.outline,
+        truncated_lines: file.metadata.truncated_lines,
+        size: file.metadata.size,
+        tokens: file.metadata.token_count,
+        sha256: &file.metadata.sha256,
+        content: &file.content,
+    }
+}
+
+fn git_json(git: &GitChanges) -> serde_json::Value {
+    json!({
+        "repository": git.repo_root.to_string_lossy(),
+        "status": git.status,
+        "staged_diff": git.staged_diff,
+        "unstaged_diff": git.unstaged_diff,
+        "diff_base": git.diff_base,
+        "base_diff": git.base_diff,
+        "log": git.log,
+    })
+}
+
+/// A single JSON document with a header, an array of files and the git section.
+pub struct JsonRenderer;
+
+impl OutputRenderer for JsonRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        let header = json!({
+            "generator": "code-flattener",
+            "version": env!("CARGO_PKG_VERSION"),
+            "roots": header.roots.iter().map(|r| r.to_string_lossy()).collect::<Vec<_>>(),
+            "profile": header.profile,
+            "git": header.repository.map(|r| json!({
+                "repository": r.root.to_string_lossy(),
+                "branch": r.branch,
+                "commit": r.commit,
+            })),
+            "tree": header.tree,
+        });
+        format!("{{\"header\":{},\"files\":[", header)
+    }
+
+    fn file(&self, file: &FlattenedFile, index: usize) -> String {
+        let separator = if index == 0 { "\n" } else { ",\n" };
+        // Serializing plain strings and numbers cannot fail
+        format!("{}{}", separator, serde_json::to_string(&json_file(file)).unwrap_or_default())
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        format!("\n],\"git_changes\":{}", git.map(git_json).unwrap_or_default())
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        format!(
+            ",\"summary\":{},\"omitted\":{},\"skipped\":{}}}\n",
+            json!({
+                "file_count": totals.file_count,
+                "token_count": totals.token_count,
+                "tokenizer": totals.tokenizer.to_string(),
+            }),
+            serde_json::Value::from_iter(totals.omitted.iter().map(omitted_json)),
+            serde_json::Value::from_iter(totals.skipped.iter().map(skipped_json))
+        )
+    }
+
+    fn wants_repository_info(&self) -> bool {
+        true
+    }
+}
+
+/// One JSON object per line, one line per file.
+pub struct JsonlRenderer;
+
+impl OutputRenderer for JsonlRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        header
+            .tree
+            .map(|tree| format!("{}\n", json!({ "type": "tree", "tree": tree })))
+            .unwrap_or_default()
+    }
+
+    fn file(&self, file: &FlattenedFile, _index: usize) -> String {
+        let mut value = serde_json::to_value(json_file(file)).unwrap_or_default();
+        value["type"] = json!("file");
+        format!("{}\n", value)
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        match git {
+            Some(git) => {
+                let mut value = git_json(git);
+                value["type"] = json!("git_changes");
+                format!("{}\n", value)
+            }
+            None => String::new(),
+        }
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        totals
+            .omitted
+            .iter()
+            .map(|file| {
+                let mut value = omitted_json(file);
+                value["type"] = json!("omitted");
+                format!("{}\n", value)
+            })
+            .chain(totals.skipped.iter().map(|file| {
+                let mut value = skipped_json(file);
+                value["type"] = json!("skipped");
+                format!("{}\n", value)
+            }))
+            .collect()
+    }
+}
+
+/// `<document>` tags inside a `<repository>` element.
+pub struct XmlRenderer;
+
+impl OutputRenderer for XmlRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        let mut out = String::from("<repository>\n");
+        for root in header.roots {
+            out.push_str(&format!("<root>{}</root>\n", xml_escape(&root.to_string_lossy())));
+        }
+        if let Some(profile) = header.profile {
+            out.push_str(&format!("<profile>{}</profile>\n", xml_escape(profile)));
+        }
+        if let Some(repo) = header.repository {
+            out.push_str(&format!("<git repository=\"{}\"", xml_escape(&repo.root.to_string_lossy())));
+            if let Some(branch) = &repo.branch {
+                out.push_str(&format!(" branch=\"{}\"", xml_escape(branch)));
+            }
+            if let Some(commit) = &repo.commit {
+                out.push_str(&format!(" commit=\"{}\"", xml_escape(commit)));
+            }
+            out.push_str("/>\n");
+        }
+        if let Some(tree) = header.tree {
+            out.push_str(&format!("<directory_tree>{}</directory_tree>\n", cdata(tree)));
+        }
+        out.push_str("<documents>\n");
+        out
+    }
+
+    fn file(&self, file: &FlattenedFile, index: usize) -> String {
+        format!(
+            "<document index=\"{}\">\n<source>{}</source>\n<document_content>{}</document_content>\n</document>\n",
+            index + 1,
+            xml_escape(&forward_slashes(&file.metadata.relative_path)),
+            cdata(&file.content)
+        )
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        let mut out = String::from("</documents>\n");
+        if let Some(git) = git {
+            out.push_str(&format!(
+                "<git_changes repository=\"{}\">\n",
+                xml_escape(&git.repo_root.to_string_lossy())
+            ));
+            for (tag, text) in [
+                ("status", &git.status),
+                ("staged_diff", &git.staged_diff),
+                ("unstaged_diff", &git.unstaged_diff),
+                ("base_diff", &git.base_diff),
+                ("log", &git.log),
+            ] {
+                if let Some(text) = text {
+                    let attributes = match (tag, &git.diff_base) {
+                        ("base_diff", Some(base)) => format!(" base=\"{}\"", xml_escape(base)),
+                        _ => String::new(),
+                    };
+                    out.push_str(&format!("<{tag}{attributes}>{}</{tag}>\n", cdata(text)));
+                }
+            }
+            out.push_str("</git_changes>\n");
+        }
+        out
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        let mut out = String::new();
+        if !totals.omitted.is_empty() {
+            out.push_str("<omitted_files reason=\"token_budget\">\n");
+            for file in totals.omitted {
+                out.push_str(&format!(
+                    "<file path=\"{}\" tokens=\"{}\"/>\n",
+                    xml_escape(&forward_slashes(&file.relative_path)),
+                    file.token_count
+                ));
+            }
+            out.push_str("</omitted_files>\n");
+        }
+        if !totals.skipped.is_empty() {
+            out.push_str("<skipped_files>\n");
+            for file in totals.skipped {
+                out.push_str(&format!(
+                    "<file path=\"{}\" reason=\"{}\"",
+                    xml_escape(&forward_slashes(&file.relative_path)),
+                    file.reason.kind()
+                ));
+                if let Some(size) = file.size {
+                    out.push_str(&format!(" size=\"{}\"", size));
+                }
+                out.push_str(&format!(">{}</file>\n", xml_escape(&file.reason.to_string())));
+            }
+            out.push_str("</skipped_files>\n");
+        }
+        out.push_str("</repository>\n");
+        out
+    }
+
+    fn wants_repository_info(&self) -> bool {
+        true
+    }
+}
+
+/// Replaces characters that are not allowed anywhere in an XML 1.0 document.
+fn xml_safe_chars(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => '\u{FFFD}',
+            c => c,
+        })
+        .collect()
+}
+
+/// Escapes text for use in XML element content and attribute values.
+fn xml_escape(text: &str) -> String {
+    xml_safe_chars(text)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Wraps text in a CDATA section, splitting any `]]>` it contains so the section cannot
+/// be terminated early.
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", xml_safe_chars(text).replace("]]>", "]]]]><![CDATA[>"))
+}
@@ -0,0 +1,100 @@
+use crate::flattener::FileMetadata;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Destination for rendered output.
+///
+/// The flattener hands over each chunk (one per file, plus the git section) as soon as
+/// it is ready and in output order, so a sink never has to hold the whole document.
+pub trait OutputSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()>;
+
+    /// Writes the chunk rendered for one file. Sinks that care about file boundaries
+    /// (e.g. [`SplitSink`](crate::SplitSink)) override this; by default it is just a chunk.
+    fn write_file(&mut self, chunk: &str, _file: &FileMetadata) -> Result<()> {
+        self.write_chunk(chunk)
+    }
+
+    /// Called once after the last chunk has been written.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams chunks into any `io::Write` (a file, stdout, a socket, ...).
+pub struct WriterSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> WriterSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl WriterSink<BufWriter<fs::File>> {
+    /// Creates (or truncates) the file at `path`, creating missing parent directories.
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create output file {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl WriterSink<BufWriter<io::Stdout>> {
+    pub fn stdout() -> Self {
+        Self::new(BufWriter::new(io::stdout()))
+    }
+}
+
+impl<W: Write> OutputSink for WriterSink<W> {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.writer.write_all(chunk.as_bytes())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Collects all chunks into a single in-memory string.
+#[derive(Debug, Default)]
+pub struct StringSink {
+    content: String,
+}
+
+impl StringSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.content
+    }
+
+    pub fn into_string(self) -> String {
+        self.content
+    }
+}
+
+impl OutputSink for StringSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.content.push_str(chunk);
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::flattener::FileMetadata;
+use crate::sink::{OutputSink, WriterSink};
+use crate::tokens::{count_tokens, Tokenizer};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Size limit for each part written by [`SplitSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    Tokens(usize),
+    Bytes(usize),
+}
+
+/// Splits the output into `name.part1.ext`, `name.part2.ext`, ... next to `path`.
+///
+/// Each file chunk goes into a single part unless the chunk alone exceeds the limit, in
+/// which case it is cut at line boundaries (and lines longer than a part within the line)
+/// and spread over as many parts as needed. Every part starts with a `Part k of n` header
+/// listing the files it contains, and `name.index.ext` maps every file to its part. Part
+/// bodies are written as soon as they are full; the headers and the index are added in
+/// [`OutputSink::finish`] once the number of parts is known, and parts left over from an
+/// earlier run with more parts are removed then.
+pub struct SplitSink {
+    path: PathBuf,
+    limit: SplitLimit,
+    tokenizer: Tokenizer,
+    /// Index of every finished part: the files (or pieces of files) it contains.
+    parts: Vec<Vec<String>>,
+    body: String,
+    body_files: Vec<String>,
+    /// Size of `body` plus its index lines, in the unit of `limit`.
+    body_size: usize,
+}
+
+impl SplitSink {
+    pub fn new(path: impl Into<PathBuf>, limit: SplitLimit, tokenizer: Tokenizer) -> Self {
+        Self {
+            path: path.into(),
+            limit,
+            tokenizer,
+            parts: Vec::new(),
+            body: String::new(),
+            body_files: Vec::new(),
+            body_size: 0,
+        }
+    }
+
+    /// Path of part `number` (1-based): `out.txt` becomes `out.part1.txt`.
+    pub fn part_path(&self, number: usize) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{}.part{}.{}", stem, number, ext.to_string_lossy()),
+            None => format!("{}.part{}", stem, number),
+        };
+        self.path.with_file_name(name)
+    }
+
+    /// Path of the index of all parts: `out.txt` becomes `out.index.txt`.
+    pub fn index_path(&self) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{}.index.{}", stem, ext.to_string_lossy()),
+            None => format!("{}.index", stem),
+        };
+        self.path.with_file_name(name)
+    }
+
+    fn max(&self) -> usize {
+        match self.limit {
+            SplitLimit::Tokens(n) | SplitLimit::Bytes(n) => n.max(1),
+        }
+    }
+
+    fn size(&self, text: &str) -> usize {
+        match self.limit {
+            SplitLimit::Tokens(_) => count_tokens(text, self.tokenizer),
+            SplitLimit::Bytes(_) => text.len(),
+        }
+    }
+
+    fn index_line(label: &str) -> String {
+        format!("#   {}\n", label)
+    }
+
+    /// Cost of the fixed header lines of a part. The number of parts is only known at the
+    /// end, so this leaves room for the widest one.
+    fn header_size(&self) -> usize {
+        self.size(&format!("# --- Part {0} of {0} ---\n# Files in this part:\n", usize::MAX))
+    }
+
+    /// Appends `text` to the current part, starting a new part first if it would not fit.
+    fn push(&mut self, text: &str, label: Option<&str>) -> Result<()> {
+        let cost = self.size(text) + label.map_or(0, |l| self.size(&Self::index_line(l)));
+        if self.body_size > 0 && self.body_size + cost > self.max() {
+            self.flush_part()?;
+        }
+        if self.body_size == 0 {
+            self.body_size = self.header_size();
+        }
+        self.body.push_str(text);
+        self.body_size += cost;
+        if let Some(label) = label {
+            self.body_files.push(label.to_string());
+        }
+        Ok(())
+    }
+
+    /// Writes a chunk, cutting it at line boundaries when it cannot fit in any part.
+    fn push_chunk(&mut self, chunk: &str, label: Option<&str>) -> Result<()> {
+        let label_cost = label.map_or(0, |l| {
+            self.size(&Self::index_line(&format!("{} (piece {1} of {1})", l, usize::MAX)))
+        });
+        let fixed = self.header_size() + label_cost;
+        if fixed + self.size(chunk) <= self.max() {
+            return self.push(chunk, label);
+        }
+
+        let room = self.max().saturating_sub(fixed).max(1);
+        let mut pieces = Vec::new();
+        let mut piece = String::new();
+        let mut piece_size = 0;
+        for line in chunk.split_inclusive('\n') {
+            for line in self.cut_line(line, room) {
+                let line_size = self.size(line);
+                if piece_size > 0 && piece_size + line_size > room {
+                    pieces.push(std::mem::take(&mut piece));
+                    piece_size = 0;
+                }
+                piece.push_str(line);
+                piece_size += line_size;
+            }
+        }
+        pieces.push(piece);
+
+        let total = pieces.len();
+        for (i, piece) in pieces.iter().enumerate() {
+            let label = label.map(|l| format!("{} (piece {} of {})", l, i + 1, total));
+            self.push(piece, label.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Cuts a line longer than `room` into pieces that fit, at character boundaries.
+    fn cut_line<'a>(&self, line: &'a str, room: usize) -> Vec<&'a str> {
+        if self.size(line) <= room {
+            return vec![line];
+        }
+        warn!("Cutting a line of {} bytes that does not fit in a part", line.len());
+        let ends: Vec<usize> = line.char_indices().map(|(i, _)| i).skip(1).chain([line.len()]).collect();
+        let mut cuts = Vec::new();
+        let mut start = 0;
+        let mut rest = &ends[..];
+        while !rest.is_empty() {
+            let fits = |end: usize| self.size(&line[start..end]) <= room;
+            // Galloping search for the longest piece that fits, keeping at least one character
+            let mut len = 1;
+            while len < rest.len() && fits(rest[len]) {
+                len *= 2;
+            }
+            let count = rest[..len.min(rest.len())].partition_point(|&end| fits(end)).max(1);
+            cuts.push(&line[start..rest[count - 1]]);
+            start = rest[count - 1];
+            rest = &rest[count..];
+        }
+        cuts
+    }
+
+    fn flush_part(&mut self) -> Result<()> {
+        let path = self.part_path(self.parts.len() + 1);
+        let mut sink = WriterSink::create(&path)?;
+        sink.write_chunk(&self.body)?;
+        sink.finish()?;
+        self.parts.push(std::mem::take(&mut self.body_files));
+        self.body.clear();
+        self.body_size = 0;
+        Ok(())
+    }
+
+    fn header(&self, number: usize, files: &[String]) -> String {
+        let mut header = format!("# --- Part {} of {} ---\n", number, self.parts.len());
+        if files.is_empty() {
+            header.push_str("# Files in this part: none\n");
+        } else {
+            header.push_str("# Files in this part:\n");
+            for file in files {
+                header.push_str(&Self::index_line(file));
+            }
+        }
+        header
+    }
+
+    /// Every part with the files it contains, in order.
+    fn index(&self) -> String {
+        let mut index = format!("# --- Index of {} parts ---\n", self.parts.len());
+        for (i, files) in self.parts.iter().enumerate() {
+            let name = self.part_path(i + 1);
+            let name = name.file_name().unwrap_or_default().to_string_lossy();
+            if files.is_empty() {
+                index.push_str(&format!("# {}: no files\n", name));
+            } else {
+                index.push_str(&format!("# {}:\n", name));
+                for file in files {
+                    index.push_str(&Self::index_line(file));
+                }
+            }
+        }
+        index
+    }
+}
+
+fn prepend(path: &Path, header: &str) -> Result<()> {
+    let body = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut file = fs::File::create(path)
+        .with_context(|| format!("Failed to rewrite output file {}", path.display()))?;
+    file.write_all(header.as_bytes())?;
+    file.write_all(&body)?;
+    Ok(())
+}
+
+impl OutputSink for SplitSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.push_chunk(chunk, None)
+    }
+
+    fn write_file(&mut self, chunk: &str, file: &FileMetadata) -> Result<()> {
+        let label = file.relative_path.to_string_lossy().replace('\\', "/");
+        self.push_chunk(chunk, Some(&label))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.body_size > 0 || self.parts.is_empty() {
+            self.flush_part()?;
+        }
+        for (i, files) in self.parts.iter().enumerate() {
+            prepend(&self.part_path(i + 1), &self.header(i + 1, files))?;
+        }
+        let mut index = WriterSink::create(&self.index_path())?;
+        index.write_chunk(&self.index())?;
+        index.finish()?;
+        // Parts of an earlier run that had more of them
+        let mut number = self.parts.len() + 1;
+        while self.part_path(number).exists() {
+            let path = self.part_path(number);
+            fs::remove_file(&path).with_context(|| format!("Failed to remove stale part {}", path.display()))?;
+            number += 1;
+        }
+        info!(
+            "Output split into {} parts: {} ... {}",
+            self.parts.len(),
+            self.part_path(1).display(),
+            self.part_path(self.parts.len()).display()
+        );
+        info!("Index of the parts written to: {}", self.index_path().display());
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::flattener::FileMetadata;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How `--stats` prints the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            other => Err(format!("unknown stats format '{}' (expected table or json)", other)),
+        }
+    }
+}
+
+impl fmt::Display for StatsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StatsFormat::Table => "table",
+            StatsFormat::Json => "json",
+        })
+    }
+}
+
+/// Cost of one file, directory or extension.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsRow {
+    pub name: String,
+    pub files: usize,
+    pub tokens: usize,
+    pub bytes: u64,
+    pub lines: usize,
+}
+
+impl StatsRow {
+    fn add(&mut self, file: &FileMetadata) {
+        self.files += 1;
+        self.tokens += file.token_count;
+        self.bytes += file.size;
+        self.lines += file.line_count;
+    }
+}
+
+/// Token, byte and line counts per file, per directory and per extension, each sorted
+/// by tokens, most expensive first.
+///
+/// A directory row covers every file below it, subdirectories included. Token counts
+/// are for file contents only; the markers the renderer adds are not attributed.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenStats {
+    pub total: StatsRow,
+    pub files: Vec<StatsRow>,
+    pub directories: Vec<StatsRow>,
+    pub extensions: Vec<StatsRow>,
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn sorted(rows: HashMap<String, StatsRow>) -> Vec<StatsRow> {
+    let mut rows: Vec<StatsRow> = rows.into_values().collect();
+    sort_rows(&mut rows);
+    rows
+}
+
+fn sort_rows(rows: &mut [StatsRow]) {
+    rows.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.name.cmp(&b.name)));
+}
+
+impl TokenStats {
+    pub fn from_files(files: &[FileMetadata]) -> Self {
+        let mut total = StatsRow { name: "total".to_string(), ..StatsRow::default() };
+        let mut per_file = Vec::with_capacity(files.len());
+        let mut directories: HashMap<String, StatsRow> = HashMap::new();
+        let mut extensions: HashMap<String, StatsRow> = HashMap::new();
+
+        for file in files {
+            total.add(file);
+
+            let mut row = StatsRow { name: display_path(&file.relative_path), ..StatsRow::default() };
+            row.add(file);
+            per_file.push(row);
+
+            for dir in file.relative_path.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() {
+                    continue;
+                }
+                let name = display_path(dir);
+                directories
+                    .entry(name.clone())
+                    .or_insert_with(|| StatsRow { name, ..StatsRow::default() })
+                    .add(file);
+            }
+
+            let extension = if file.extension.is_empty() {
+                "(none)".to_string()
+            } else {
+                format!(".{}", file.extension)
+            };
+            extensions
+                .entry(extension.clone())
+                .or_insert_with(|| StatsRow { name: extension, ..StatsRow::default() })
+                .add(file);
+        }
+        sort_rows(&mut per_file);
+
+        Self {
+            total,
+            files: per_file,
+            directories: sorted(directories),
+            extensions: sorted(extensions),
+        }
+    }
+
+    /// Renders the report as aligned text tables, showing at most `limit` rows per section.
+    pub fn to_table(&self, limit: usize) -> String {
+        let sections = [
+            ("Files", &self.files),
+            ("Directories", &self.directories),
+            ("Extensions", &self.extensions),
+        ];
+        let width = sections
+            .iter()
+            .flat_map(|(title, rows)| {
+                rows.iter().take(limit).map(|r| r.name.len()).chain([title.len()])
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut out = String::new();
+        for (title, rows) in sections {
+            if rows.is_empty() {
+                continue;
+            }
+            out.push_str(&format!(
+                "{:<width$}  {:>6}  {:>10}  {:>12}  {:>8}  {:>6}\n",
+                title, "files", "tokens", "bytes", "lines", "share"
+            ));
+            for row in rows.iter().take(limit) {
+                out.push_str(&self.table_row(row, width));
+            }
+            if rows.len() > limit {
+                out.push_str(&format!("... {} more\n", rows.len() - limit));
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.table_row(&self.total, width));
+        out
+    }
+
+    fn table_row(&self, row: &StatsRow, width: usize) -> String {
+        let share = if self.total.tokens == 0 {
+            0.0
+        } else {
+            row.tokens as f64 * 100.0 / self.total.tokens as f64
+        };
+        format!(
+            "{:<width$}  {:>6}  {:>10}  {:>12}  {:>8}  {:>5.1}%\n",
+            row.name, row.files, row.tokens, row.bytes, row.lines, share
+        )
+    }
+}
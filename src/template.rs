@@ -0,0 +1,246 @@
+use crate::flattener::FlattenedFile;
+use crate::git::GitChanges;
+use crate::render::{OutputHeader, OutputRenderer, OutputTotals};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const PREAMBLE_FIELDS: &[&str] = &["root", "roots", "profile", "branch", "commit", "tree"];
+const FILE_FIELDS: &[&str] = &[
+    "path", "absolute_path", "lang", "extension", "encoding", "outline", "truncated_lines", "content", "tokens", "size", "sha256",
+    "index",
+];
+const GIT_FIELDS: &[&str] = &["repository", "status", "staged_diff", "unstaged_diff", "diff_base", "base_diff", "log"];
+const EPILOGUE_FIELDS: &[&str] = &[
+    "file_count", "token_count", "tokenizer", "omitted", "omitted_count", "skipped", "skipped_count",
+];
+
+enum Segment {
+    Text(String),
+    Field(String),
+}
+
+/// One parsed section of a template: literal text interleaved with `{{field}}` placeholders.
+struct Section {
+    segments: Vec<Segment>,
+}
+
+impl Section {
+    fn parse(text: &str, name: &str, fields: &[&str]) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else { break };
+            let field = rest[start + 2..start + 2 + len].trim();
+            if !fields.contains(&field) {
+                anyhow::bail!(
+                    "Unknown placeholder '{{{{{}}}}}' in {} section (available: {})",
+                    field,
+                    name,
+                    fields.join(", ")
+                );
+            }
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            segments.push(Segment::Field(field.to_string()));
+            rest = &rest[start + 2 + len + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    fn uses(&self, field: &str) -> bool {
+        self.segments.iter().any(|s| matches!(s, Segment::Field(f) if f == field))
+    }
+
+    /// Substitutes placeholders in a single pass, so placeholder-like text inside
+    /// substituted values (e.g. file content) is never expanded.
+    fn render(&self, value: impl Fn(&str) -> String) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Field(field) => out.push_str(&value(field)),
+            }
+        }
+        out
+    }
+}
+
+/// Renders output from a user-supplied template file.
+///
+/// A template is either a single per-file template, or up to four sections:
+///
+/// ```text
+/// {{#preamble}}Repository: {{root}} ({{branch}} @ {{commit}})
+/// {{/preamble}}
+/// {{#file}}<file path="{{path}}" lang="{{lang}}" tokens="{{tokens}}">
+/// {{content}}
+/// </file>
+/// {{/file}}
+/// {{#git}}Status:
+/// {{status}}
+/// {{/git}}
+/// {{#epilogue}}{{file_count}} files, {{token_count}} tokens
+/// {{/epilogue}}
+/// ```
+///
+/// A single newline directly after an opening section tag is dropped. Unknown
+/// placeholders are rejected when the template is loaded.
+pub struct TemplateRenderer {
+    preamble: Option<Section>,
+    file: Section,
+    git: Option<Section>,
+    epilogue: Option<Section>,
+}
+
+impl TemplateRenderer {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let template = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template file: {}", path.display()))?;
+        Self::parse(&template)
+            .with_context(|| format!("Invalid template file: {}", path.display()))
+    }
+
+    pub fn parse(template: &str) -> Result<Self> {
+        if !template.contains("{{#") {
+            return Ok(Self {
+                preamble: None,
+                file: Section::parse(template, "file", FILE_FIELDS)?,
+                git: None,
+                epilogue: None,
+            });
+        }
+
+        let mut renderer = Self {
+            preamble: None,
+            file: Section { segments: Vec::new() },
+            git: None,
+            epilogue: None,
+        };
+        let mut rest = template;
+        while let Some(open) = rest.find("{{#") {
+            if !rest[..open].trim().is_empty() {
+                anyhow::bail!("Text outside of a section: {:?}", rest[..open].trim());
+            }
+            let Some(name_len) = rest[open + 3..].find("}}") else {
+                anyhow::bail!("Unterminated section tag");
+            };
+            let name = rest[open + 3..open + 3 + name_len].trim().to_string();
+            let body_start = open + 3 + name_len + 2;
+            let close_tag = format!("{{{{/{}}}}}", name);
+            let Some(body_len) = rest[body_start..].find(&close_tag) else {
+                anyhow::bail!("Missing closing tag {}", close_tag);
+            };
+            let body = &rest[body_start..body_start + body_len];
+            let body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+
+            match name.as_str() {
+                "preamble" => renderer.preamble = Some(Section::parse(body, &name, PREAMBLE_FIELDS)?),
+                "file" => renderer.file = Section::parse(body, &name, FILE_FIELDS)?,
+                "git" => renderer.git = Some(Section::parse(body, &name, GIT_FIELDS)?),
+                "epilogue" => renderer.epilogue = Some(Section::parse(body, &name, EPILOGUE_FIELDS)?),
+                other => anyhow::bail!(
+                    "Unknown section '{}' (expected preamble, file, git or epilogue)",
+                    other
+                ),
+            }
+            rest = &rest[body_start + body_len + close_tag.len()..];
+        }
+        if !rest.trim().is_empty() {
+            anyhow::bail!("Text outside of a section: {:?}", rest.trim());
+        }
+        Ok(renderer)
+    }
+}
+
+impl OutputRenderer for TemplateRenderer {
+    fn preamble(&self, header: &OutputHeader) -> String {
+        let Some(section) = &self.preamble else { return String::new() };
+        section.render(|field| match field {
+            "root" => header
+                .roots
+                .first()
+                .map(|r| r.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "roots" => header
+                .roots
+                .iter()
+                .map(|r| r.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "profile" => header.profile.unwrap_or_default().to_string(),
+            "branch" => header.repository.and_then(|r| r.branch.clone()).unwrap_or_default(),
+            "commit" => header.repository.and_then(|r| r.commit.clone()).unwrap_or_default(),
+            "tree" => header.tree.unwrap_or_default().to_string(),
+            _ => String::new(),
+        })
+    }
+
+    fn file(&self, file: &FlattenedFile, index: usize) -> String {
+        let meta = &file.metadata;
+        self.file.render(|field| match field {
+            "path" => meta.relative_path.to_string_lossy().replace('\\', "/"),
+            "absolute_path" => meta.path.to_string_lossy().into_owned(),
+            "lang" => meta.language.unwrap_or(&meta.extension).to_string(),
+            "extension" => meta.extension.clone(),
+            "encoding" => meta.encoding.to_string(),
+            "outline" => meta.outline.to_string(),
+            "truncated_lines" => meta.truncated_lines.to_string(),
+            "content" => file.content.clone(),
+            "tokens" => meta.token_count.to_string(),
+            "size" => meta.size.to_string(),
+            "sha256" => meta.sha256.clone(),
+            "index" => (index + 1).to_string(),
+            _ => String::new(),
+        })
+    }
+
+    fn git_section(&self, git: Option<&GitChanges>) -> String {
+        let (Some(section), Some(git)) = (&self.git, git) else { return String::new() };
+        section.render(|field| match field {
+            "repository" => git.repo_root.to_string_lossy().into_owned(),
+            "status" => git.status.clone().unwrap_or_default(),
+            "staged_diff" => git.staged_diff.clone().unwrap_or_default(),
+            "unstaged_diff" => git.unstaged_diff.clone().unwrap_or_default(),
+            "diff_base" => git.diff_base.clone().unwrap_or_default(),
+            "base_diff" => git.base_diff.clone().unwrap_or_default(),
+            "log" => git.log.clone().unwrap_or_default(),
+            _ => String::new(),
+        })
+    }
+
+    fn epilogue(&self, totals: &OutputTotals) -> String {
+        let Some(section) = &self.epilogue else { return String::new() };
+        section.render(|field| match field {
+            "file_count" => totals.file_count.to_string(),
+            "token_count" => totals.token_count.to_string(),
+            "tokenizer" => totals.tokenizer.to_string(),
+            "omitted" => totals
+                .omitted
+                .iter()
+                .map(|f| format!("{} ({} tokens)\n", f.relative_path.to_string_lossy(), f.token_count))
+                .collect(),
+            "omitted_count" => totals.omitted.len().to_string(),
+            "skipped" => totals
+                .skipped
+                .iter()
+                .map(|f| format!("{}: {}\n", f.relative_path.to_string_lossy(), f.reason))
+                .collect(),
+            "skipped_count" => totals.skipped.len().to_string(),
+            _ => String::new(),
+        })
+    }
+
+    fn wants_repository_info(&self) -> bool {
+        self.preamble
+            .as_ref()
+            .is_some_and(|p| p.uses("branch") || p.uses("commit"))
+    }
+}
@@ -0,0 +1,77 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton, p50k_base_singleton};
+
+/// Average number of characters per token assumed by [`Tokenizer::Estimate`]; close to
+/// what the BPE encoders produce for source code and English text.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How tokens are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tokenizer {
+    /// GPT-4 / GPT-3.5-turbo encoding.
+    #[serde(alias = "cl100k")]
+    Cl100kBase,
+    /// GPT-4o encoding.
+    #[serde(alias = "o200k")]
+    O200kBase,
+    /// Codex / text-davinci encoding.
+    #[serde(alias = "p50k")]
+    P50kBase,
+    /// Character count divided by four: fast, no encoder needed.
+    #[serde(alias = "approximate")]
+    Estimate,
+    /// Whitespace-separated words.
+    #[default]
+    Whitespace,
+}
+
+impl FromStr for Tokenizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cl100k_base" | "cl100k" => Ok(Tokenizer::Cl100kBase),
+            "o200k_base" | "o200k" => Ok(Tokenizer::O200kBase),
+            "p50k_base" | "p50k" => Ok(Tokenizer::P50kBase),
+            "estimate" | "approximate" => Ok(Tokenizer::Estimate),
+            "whitespace" => Ok(Tokenizer::Whitespace),
+            other => Err(format!(
+                "unknown tokenizer '{}' (expected cl100k_base, o200k_base, p50k_base, estimate or whitespace)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Tokenizer::Cl100kBase => "cl100k_base",
+            Tokenizer::O200kBase => "o200k_base",
+            Tokenizer::P50kBase => "p50k_base",
+            Tokenizer::Estimate => "estimate",
+            Tokenizer::Whitespace => "whitespace",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Tokenizer {
+    pub fn count(self, text: &str) -> usize {
+        match self {
+            Tokenizer::Cl100kBase => cl100k_base_singleton().encode_with_special_tokens(text).len(),
+            Tokenizer::O200kBase => o200k_base_singleton().encode_with_special_tokens(text).len(),
+            Tokenizer::P50kBase => p50k_base_singleton().encode_with_special_tokens(text).len(),
+            Tokenizer::Estimate => text.chars().count().div_ceil(CHARS_PER_TOKEN),
+            Tokenizer::Whitespace => text.split_whitespace().count(),
+        }
+    }
+}
+
+/// Counts tokens in `text` with `tokenizer`.
+pub fn count_tokens(text: &str, tokenizer: Tokenizer) -> usize {
+    tokenizer.count(text)
+}
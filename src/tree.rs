@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    /// Set on file nodes that were walked but filtered out.
+    excluded: bool,
+}
+
+impl Node {
+    fn insert(&mut self, relative_path: &Path, excluded: bool) {
+        let mut node = self;
+        for component in relative_path.iter() {
+            node = node
+                .children
+                .entry(component.to_string_lossy().into_owned())
+                .or_default();
+        }
+        node.excluded = excluded;
+    }
+
+    fn render(&self, prefix: &str, out: &mut String) {
+        let count = self.children.len();
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let last = i + 1 == count;
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            out.push_str(name);
+            if !child.children.is_empty() {
+                out.push('/');
+            } else if child.excluded {
+                out.push_str(" [excluded]");
+            }
+            out.push('\n');
+            child.render(&format!("{}{}", prefix, if last { "    " } else { "│   " }), out);
+        }
+    }
+}
+
+/// ASCII directory tree (in the style of `tree`) of the files found under one target
+/// directory. Entries are sorted by name regardless of the output order.
+pub(crate) struct DirectoryTree {
+    root: Node,
+}
+
+impl DirectoryTree {
+    pub(crate) fn new() -> Self {
+        Self { root: Node::default() }
+    }
+
+    /// Adds a file, given relative to the target directory.
+    pub(crate) fn add(&mut self, relative_path: &Path, excluded: bool) {
+        self.root.insert(relative_path, excluded);
+    }
+
+    /// Renders the tree with `label` (usually the directory name) as its first line.
+    pub(crate) fn render(&self, label: &str) -> String {
+        let mut out = format!("{}/\n", label.trim_end_matches(['/', '\\']));
+        self.root.render("", &mut out);
+        out
+    }
+}
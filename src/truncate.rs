@@ -0,0 +1,145 @@
+use crate::filters::match_glob;
+use crate::options::FlattenOptions;
+use crate::tokens::{count_tokens, Tokenizer};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How much of a file over the size limit to keep instead of skipping it: the first
+/// `head` and last `tail` lines, or as many whole lines as fit in `head` and `tail`
+/// tokens. The lines in between are replaced by a `... [truncated K lines] ...` marker.
+///
+/// Written as `HEAD:TAIL`, optionally followed by `lines` (the default) or `tokens`, e.g.
+/// `200:50` or `2000:500 tokens`; a single number keeps only the head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Truncation {
+    Lines { head: usize, tail: usize },
+    Tokens { head: usize, tail: usize },
+}
+
+impl FromStr for Truncation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec = s.trim().to_lowercase();
+        let (counts, tokens) = if let Some(counts) = spec.strip_suffix("tokens") {
+            (counts, true)
+        } else {
+            (spec.strip_suffix("lines").unwrap_or(&spec), false)
+        };
+        let invalid = || format!("invalid truncation '{}' (expected HEAD:TAIL [lines|tokens], e.g. 200:50)", s);
+        let (head, tail) = counts.trim().split_once(':').unwrap_or((counts.trim(), "0"));
+        let head = head.trim().parse().map_err(|_| invalid())?;
+        let tail = tail.trim().parse().map_err(|_| invalid())?;
+        Ok(if tokens { Truncation::Tokens { head, tail } } else { Truncation::Lines { head, tail } })
+    }
+}
+
+impl TryFrom<String> for Truncation {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Truncation::Lines { head, tail } => write!(f, "{}:{} lines", head, tail),
+            Truncation::Tokens { head, tail } => write!(f, "{}:{} tokens", head, tail),
+        }
+    }
+}
+
+/// A truncation for oversized files matching a glob, written `GLOB=HEAD:TAIL`, e.g.
+/// `**/*.sql=300:50` or `**/*.log=0:2000 tokens`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TruncateRule {
+    pub glob: String,
+    pub truncation: Truncation,
+}
+
+impl TruncateRule {
+    pub fn new(glob: impl Into<String>, truncation: Truncation) -> Self {
+        Self { glob: glob.into(), truncation }
+    }
+}
+
+impl FromStr for TruncateRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (glob, spec) = s
+            .rsplit_once('=')
+            .filter(|(glob, _)| !glob.trim().is_empty())
+            .ok_or_else(|| format!("invalid truncate rule '{}' (expected GLOB=HEAD:TAIL, e.g. **/*.sql=300:50)", s))?;
+        Ok(TruncateRule::new(glob.trim(), spec.parse()?))
+    }
+}
+
+impl TryFrom<String> for TruncateRule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for TruncateRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.glob, self.truncation)
+    }
+}
+
+impl Truncation {
+    /// Keeps the head and tail of `content`. Returns the content unchanged when nothing
+    /// would be cut, and the number of lines removed otherwise.
+    pub(crate) fn apply(&self, content: String, tokenizer: Tokenizer) -> (String, usize) {
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let (head, tail) = match *self {
+            Truncation::Lines { head, tail } => (head.min(lines.len()), tail.min(lines.len())),
+            Truncation::Tokens { head, tail } => (
+                lines_within(lines.iter(), head, tokenizer),
+                lines_within(lines.iter().rev(), tail, tokenizer),
+            ),
+        };
+        if head + tail >= lines.len() {
+            return (content, 0);
+        }
+        let removed = lines.len() - head - tail;
+        let mut out: String = lines[..head].concat();
+        out.push_str(&format!("... [truncated {} lines] ...\n", removed));
+        out.push_str(&lines[lines.len() - tail..].concat());
+        (out, removed)
+    }
+}
+
+/// Number of leading `lines` whose tokens add up to at most `budget`.
+fn lines_within<'a>(lines: impl Iterator<Item = &'a &'a str>, budget: usize, tokenizer: Tokenizer) -> usize {
+    let mut used = 0;
+    lines
+        .take_while(|line| {
+            used += count_tokens(line, tokenizer);
+            used <= budget
+        })
+        .count()
+}
+
+/// The truncation that applies to a file over the size limit at `relative_path`, if
+/// it should be kept rather than skipped: that of the first matching `truncate_rules`
+/// entry, or else `truncate` within `truncate_globs`.
+pub(crate) fn truncation_for(options: &FlattenOptions, relative_path: &Path) -> Option<Truncation> {
+    let rule = options.truncate_rules.iter().flatten().find(|rule| match_glob(&rule.glob, relative_path));
+    if let Some(rule) = rule {
+        return Some(rule.truncation);
+    }
+    let truncation = options.truncate?;
+    match &options.truncate_globs {
+        Some(globs) if !globs.iter().any(|glob| match_glob(glob, relative_path)) => None,
+        _ => Some(truncation),
+    }
+}
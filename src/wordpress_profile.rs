@@ -139,7 +139,7 @@ impl WordPressProfilePlugin {
                 }
             }
 
-            let mut profile = Profile::new(
+            let profile = Profile::new(
                 "WordPress site with specific theme/plugins.".to_string(),
                 allowed_extensions,
                 allowed_filenames,
@@ -206,22 +206,22 @@ impl WordPressProfilePlugin {
             Vec::new()
         };
 
-        if plugin_names.is_empty() {
-            if let Ok(av) = self.get_available_plugins() {
-                plugin_names = av
-                    .iter()
-                    .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()))
-                    .collect();
-            }
+        if plugin_names.is_empty()
+            && let Ok(av) = self.get_available_plugins()
+        {
+            plugin_names = av
+                .iter()
+                .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()))
+                .collect();
         }
 
         for plugin in plugin_names {
              let slug = plugin.split('/').next().unwrap_or(&plugin).to_string();
-             if let Some(excludes) = exclude_plugins {
-                 if excludes.iter().any(|e| e.to_lowercase() == slug.to_lowercase()) {
-                     info!("Excluding plugin '{}'", slug);
-                     continue;
-                 }
+             if let Some(excludes) = exclude_plugins
+                 && excludes.iter().any(|e| e.to_lowercase() == slug.to_lowercase())
+             {
+                 info!("Excluding plugin '{}'", slug);
+                 continue;
              }
 
              let plugin_dir = wp_path.join("wp-content/plugins").join(&slug);
@@ -252,16 +252,14 @@ impl WordPressProfilePlugin {
         if let Ok(output) = Command::new("wp")
             .args(["plugin", "list", "--format=json", "--status=active"])
             .output()
+            && output.status.success()
+            && let Ok(plugins) = serde_json::from_slice::<Vec<serde_json::Value>>(&output.stdout)
         {
-            if output.status.success() {
-                if let Ok(plugins) = serde_json::from_slice::<Vec<serde_json::Value>>(&output.stdout) {
-                    let paths = plugins
-                        .iter()
-                        .filter_map(|p| p.get("name").and_then(|n| n.as_str()).map(|s| PathBuf::from("wp-content/plugins").join(s)))
-                        .collect();
-                    return Ok(paths);
-                }
-            }
+            let paths = plugins
+                .iter()
+                .filter_map(|p| p.get("name").and_then(|n| n.as_str()).map(|s| PathBuf::from("wp-content/plugins").join(s)))
+                .collect();
+            return Ok(paths);
         }
         self.get_available_plugins()
     }
@@ -272,12 +270,12 @@ impl WordPressProfilePlugin {
         let mut res = Vec::new();
         if let Ok(entries) = std::fs::read_dir(&plugins_dir) {
             for entry in entries.flatten() {
-                if let Ok(ft) = entry.file_type() {
-                    if ft.is_dir() {
-                        if let Some(n) = entry.file_name().to_str() {
-                            if !n.starts_with('.') { res.push(entry.path()); }
-                        }
-                    }
+                if let Ok(ft) = entry.file_type()
+                    && ft.is_dir()
+                    && let Some(n) = entry.file_name().to_str()
+                    && !n.starts_with('.')
+                {
+                    res.push(entry.path());
                 }
             }
         }
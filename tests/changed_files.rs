@@ -0,0 +1,79 @@
+mod common;
+
+use assert_cmd::Command as CargoCommand;
+use code_flattener::{Decision, FlattenOptions, Flattener, SkipReason};
+use predicates::str::contains;
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::{git, write};
+
+/// `feature` changes `src/parser.rs` in a commit, `util/fmt.rs` in the working tree and
+/// adds an untracked `notes/todo.rs`.
+fn repository() -> anyhow::Result<tempfile::TempDir> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    git(root, &["init", "-q", "-b", "main"])?;
+    for path in ["src/parser.rs", "src/lexer.rs", "util/fmt.rs", "tests/parser.rs", "tests/fmt_test.rs", "tests/other.rs"] {
+        write(root, path, "// v1\n")?;
+    }
+    git(root, &["add", "-A"])?;
+    git(root, &["commit", "-q", "-m", "Initial"])?;
+    git(root, &["checkout", "-q", "-b", "feature"])?;
+    write(root, "src/parser.rs", "// v2\n")?;
+    git(root, &["commit", "-q", "-am", "Parser"])?;
+    write(root, "util/fmt.rs", "// v2\n")?;
+    write(root, "notes/todo.rs", "// new\n")?;
+    Ok(dir)
+}
+
+fn flattened(options: FlattenOptions) -> anyhow::Result<Vec<String>> {
+    let result = Flattener::new(options).run()?;
+    let mut paths: Vec<String> =
+        result.files.iter().map(|f| f.metadata.relative_path.to_string_lossy().replace('\\', "/")).collect();
+    paths.sort();
+    Ok(paths)
+}
+
+#[test]
+fn walk_is_restricted_to_changed_files() -> anyhow::Result<()> {
+    let dir = repository()?;
+    let options = || FlattenOptions::default().target_dirs([dir.path()]).extensions([".rs"]);
+
+    assert_eq!(flattened(options().changed_since("main"))?, ["notes/todo.rs", "src/parser.rs", "util/fmt.rs"]);
+    assert_eq!(flattened(options().changed_only(true))?, ["notes/todo.rs", "util/fmt.rs"]);
+    assert_eq!(
+        flattened(options().changed_since("main").changed_neighbors(true))?,
+        ["notes/todo.rs", "src/lexer.rs", "src/parser.rs", "tests/fmt_test.rs", "tests/parser.rs", "util/fmt.rs"]
+    );
+
+    let explanations = Flattener::new(options().changed_since("main")).explain()?;
+    let decision = |path: &str| {
+        explanations.iter().find(|e| e.relative_path == Path::new(path)).map(|e| e.decision.clone()).unwrap()
+    };
+    assert_eq!(decision("src/lexer.rs"), Decision::Excluded(SkipReason::Unchanged("since main".to_string())));
+    assert!(matches!(decision("src/parser.rs"), Decision::Included(rule) if rule.ends_with(", changed since main")));
+
+    let error = Flattener::new(options().changed_since("no-such-branch")).run().unwrap_err();
+    assert_eq!(error.to_string(), "Cannot resolve --changed-since ref 'no-such-branch'");
+    assert!(format!("{:#}", error).contains(": Cannot resolve git ref 'no-such-branch'"));
+    Ok(())
+}
+
+#[test]
+fn neighbors_need_a_change_filter() -> anyhow::Result<()> {
+    let dir = repository()?;
+    CargoCommand::cargo_bin("code-flattener")?
+        .current_dir(dir.path())
+        .args(["--extensions", ".rs", "--changed-neighbors", "."])
+        .assert()
+        .failure()
+        .stderr(contains("required arguments were not provided"));
+    CargoCommand::cargo_bin("code-flattener")?
+        .current_dir(dir.path())
+        .args(["--extensions", ".rs", "--changed-since", "main", "--changed-only", "."])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs git in `root` as a fixed author, failing if it exits unsuccessfully.
+pub fn git(root: &Path, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Jane Doe", "-c", "user.email=jane@example.com"])
+        .args(args)
+        .current_dir(root)
+        .status()?;
+    anyhow::ensure!(status.success(), "git {:?} failed", args);
+    Ok(())
+}
+
+/// Writes `content` to `path` under `root`, creating its parent directories.
+pub fn write(root: &Path, path: &str, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, content)
+}
@@ -0,0 +1,33 @@
+use code_flattener::{FlattenOptions, Flattener, OutputFormat};
+use std::fs;
+use tempfile::tempdir;
+
+// The tree at the top of the output must reflect exactly the files that pass the filters.
+
+#[test]
+fn tree_lists_included_and_marks_excluded_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path().join("proj");
+    fs::create_dir_all(root.join("src/sub"))?;
+    fs::create_dir_all(root.join("docs"))?;
+    fs::write(root.join("src/lib.rs"), "pub fn a() {}\n")?;
+    fs::write(root.join("src/sub/m.rs"), "fn m() {}\n")?;
+    fs::write(root.join("README.md"), "# Title\n")?;
+    fs::write(root.join("docs/notes.txt"), "notes\n")?;
+
+    let options = FlattenOptions::new().target_dir(&root).extensions([".rs", ".md"]);
+
+    let included_only = Flattener::new(options.clone().tree(true)).run()?.content;
+    assert!(included_only.starts_with(
+        "# --- Directory Tree ---\n\nproj/\n├── README.md\n└── src/\n    ├── lib.rs\n    └── sub/\n        └── m.rs\n"
+    ));
+
+    let with_excluded = Flattener::new(options.clone().tree_excluded(true)).run()?.content;
+    assert!(with_excluded.contains("├── docs/\n│   └── notes.txt [excluded]\n└── src/\n"));
+
+    let markdown = Flattener::new(options.tree(true).format(OutputFormat::Markdown)).run()?.content;
+    assert!(markdown.starts_with("# --- Directory Tree ---\n```text\nproj/\n"));
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use code_flattener::{EncodingPolicy, FlattenOptions, Flattener, OutputFormat};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn utf16(text: &str, big_endian: bool, bom: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let units = if bom { vec![0xFEFF] } else { Vec::new() };
+    for unit in units.into_iter().chain(text.encode_utf16()) {
+        bytes.extend(if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() });
+    }
+    bytes
+}
+
+fn flatten(root: &Path, policy: EncodingPolicy) -> anyhow::Result<Vec<(String, String, &'static str)>> {
+    let options = FlattenOptions::new().target_dir(root).extensions([".txt"]).encoding_fallback(policy);
+    Ok(Flattener::new(options)
+        .run()?
+        .files
+        .into_iter()
+        .map(|f| (f.metadata.relative_path.to_string_lossy().into_owned(), f.content, f.metadata.encoding))
+        .collect())
+}
+
+#[test]
+fn legacy_and_utf16_files_are_transcoded() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("a_utf8.txt"), "café\n")?;
+    fs::write(root.join("b_bom.txt"), b"\xef\xbb\xbfcaf\xc3\xa9\n")?;
+    fs::write(root.join("c_cp1252.txt"), b"Le caf\xe9 co\xfbte 2\x80 \x96 d\xe9j\xe0 pay\xe9.\n")?;
+    fs::write(root.join("d_utf16le.txt"), utf16("café = coffee\n", false, true))?;
+    fs::write(root.join("e_utf16be.txt"), utf16("café = coffee\n", true, true))?;
+    fs::write(root.join("f_utf16le_nobom.txt"), utf16("plain ascii text here\n", false, false))?;
+
+    let files = flatten(root, EncodingPolicy::Transcode)?;
+    let expected = [
+        ("a_utf8.txt", "café\n", "UTF-8"),
+        ("b_bom.txt", "café\n", "UTF-8"),
+        ("c_cp1252.txt", "Le café coûte 2€ – déjà payé.\n", "windows-1252"),
+        ("d_utf16le.txt", "café = coffee\n", "UTF-16LE"),
+        ("e_utf16be.txt", "café = coffee\n", "UTF-16BE"),
+        ("f_utf16le_nobom.txt", "plain ascii text here\n", "UTF-16LE"),
+    ];
+    assert_eq!(files.len(), expected.len());
+    for ((path, content, encoding), (exp_path, exp_content, exp_encoding)) in files.iter().zip(expected) {
+        assert_eq!((path.as_str(), content.as_str(), *encoding), (exp_path, exp_content, exp_encoding));
+    }
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn fallback_policies() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("ok.txt"), "fine\n")?;
+    fs::write(root.join("latin1.txt"), b"caf\xe9\n")?;
+
+    let lossy = flatten(root, EncodingPolicy::Lossy)?;
+    assert_eq!(lossy[0].1, "caf\u{FFFD}\n");
+    assert_eq!(lossy[0].2, "UTF-8 (lossy)");
+
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".txt"])
+        .encoding_fallback(EncodingPolicy::Skip)
+        .list_skipped(true);
+    let result = Flattener::new(options).run()?;
+    assert_eq!(result.file_count, 1);
+    assert_eq!(result.skipped[0].reason.kind(), "not_utf8");
+
+    assert!(flatten(root, EncodingPolicy::Error).is_err());
+
+    // The detected encoding is part of the JSON file objects
+    let options = FlattenOptions::new().target_dir(root).extensions([".txt"]).format(OutputFormat::Json);
+    let value: serde_json::Value = serde_json::from_str(&Flattener::new(options).run()?.content)?;
+    assert_eq!(value["files"][0]["encoding"], "windows-1252");
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use assert_cmd::Command;
+use code_flattener::{Decision, FlattenOptions, Flattener, SkipReason};
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::write;
+
+#[test]
+fn explanations_agree_with_the_output_and_name_the_rule() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    write(root, "src/lib.rs", b"pub fn f() {}\n")?;
+    write(root, "src/gen/out.rs", b"x\n")?;
+    write(root, "src/gen/keep.rs", b"x\n")?;
+    write(root, "src/big.rs", vec![b'a'; 4096])?;
+    write(root, "logo.png", b"png")?;
+    write(root, "blob.rs", b"\x00\x01\x02")?;
+    write(root, "tests/a.rs", b"x\n")?;
+    write(root, "notes.md", b"x\n")?;
+    write(root, "src/.flattenerignore", b"# generated\ngen/*\n!gen/keep.rs\n")?;
+
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs", ".png"])
+        .max_size(0.001)
+        .exclude_globs(["tests/*"]);
+    let flattener = Flattener::new(options);
+    let explanations = flattener.explain()?;
+
+    let mut included: Vec<String> = explanations
+        .iter()
+        .filter(|e| e.is_included())
+        .map(|e| e.relative_path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    included.sort();
+    let mut output: Vec<String> = flattener
+        .run()?
+        .files
+        .iter()
+        .map(|f| f.metadata.relative_path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    output.sort();
+    assert_eq!(included, output);
+    assert_eq!(included, ["src/gen/keep.rs", "src/lib.rs"]);
+
+    let reason = |path: &str| {
+        explanations
+            .iter()
+            .find(|e| e.relative_path == Path::new(path))
+            .map(|e| e.decision.clone())
+            .unwrap_or_else(|| panic!("no explanation for {}", path))
+    };
+    assert_eq!(reason("logo.png"), Decision::Excluded(SkipReason::BinaryExtension("png".into())));
+    assert_eq!(reason("blob.rs"), Decision::Excluded(SkipReason::BinaryContent));
+    assert_eq!(reason("tests/a.rs"), Decision::Excluded(SkipReason::ExcludeGlob("tests/*".into())));
+    assert_eq!(reason("notes.md"), Decision::Excluded(SkipReason::NotAllowed));
+    assert!(matches!(reason("src/big.rs"), Decision::Excluded(SkipReason::TooLarge { size: 4096, .. })));
+    match reason("src/gen/out.rs") {
+        Decision::Excluded(SkipReason::IgnoreFile { line, pattern, .. }) => {
+            assert_eq!((line, pattern.as_str()), (Some(2), "gen/*"));
+        }
+        other => panic!("unexpected decision {:?}", other),
+    }
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn explain_single_path_from_cli() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    write(root, "node_modules/pkg/index.js", b"x\n")?;
+    write(root, "app.js", b"x\n")?;
+
+    let output = Command::cargo_bin("code-flattener")?
+        .current_dir(root)
+        .args(["--extensions", ".js", "--exclude-node-modules", "--explain=node_modules/pkg/index.js", "."])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("exclude  node_modules/: directory 'node_modules' skipped by exclude_node_modules"));
+    assert!(!stdout.contains("app.js"));
+
+    dir.close()?;
+    Ok(())
+}
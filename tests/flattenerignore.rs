@@ -0,0 +1,52 @@
+use code_flattener::{FlattenOptions, Flattener};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// .flattenerignore files follow .gitignore rules and are read from the target tree, not
+// from the current working directory.
+
+fn included(root: &Path) -> anyhow::Result<Vec<String>> {
+    let options = FlattenOptions::new().target_dir(root).extensions([".rs", ".txt"]);
+    Ok(Flattener::new(options)
+        .run()?
+        .files
+        .iter()
+        .map(|f| f.metadata.relative_path.to_string_lossy().replace('\\', "/"))
+        .collect())
+}
+
+#[test]
+fn nested_ignore_files_with_negation_anchoring_and_directories() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    for path in [
+        "build.rs",
+        "notes.txt",
+        "src/lib.rs",
+        "src/build.rs",
+        "src/gen/out.rs",
+        "src/gen/keep.rs",
+        "src/fixtures/data.txt",
+        "src/fixtures/important.txt",
+        "vendor.txt/readme.rs",
+    ] {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, "x\n")?;
+    }
+
+    // Root: anchored pattern only matches the top-level build.rs; `vendor.txt/` only
+    // matches the directory, not notes.txt
+    fs::write(root.join(".flattenerignore"), "# comment\n/build.rs\n*.txt\n!notes.txt\nvendor.txt/\n")?;
+    // Nested: ignore a directory but re-include one file in it
+    fs::write(root.join("src").join(".flattenerignore"), "gen/*\n!gen/keep.rs\n!fixtures/important.txt\n")?;
+
+    assert_eq!(
+        included(root)?,
+        ["notes.txt", "src/build.rs", "src/fixtures/important.txt", "src/gen/keep.rs", "src/lib.rs"]
+    );
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use code_flattener::{FlattenOptions, Flattener};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::git;
+
+fn commit(root: &Path, files: &[(&str, Option<&str>)], message: &str) -> anyhow::Result<()> {
+    for (name, content) in files {
+        match content {
+            Some(content) => fs::write(root.join(name), content)?,
+            None => fs::remove_file(root.join(name))?,
+        }
+    }
+    git(root, &["add", "-A"])?;
+    git(root, &["commit", "-q", "-m", message])
+}
+
+/// A `main` branch and a `feature` branch forked from it, with commits on both.
+fn repository() -> anyhow::Result<tempfile::TempDir> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    git(root, &["init", "-q", "-b", "main"])?;
+    commit(
+        root,
+        &[("lib.rs", Some("pub fn a() {}\n")), ("old.rs", Some("pub fn old() {}\n")), ("notes.txt", Some("v1\n"))],
+        "Initial",
+    )?;
+    git(root, &["checkout", "-q", "-b", "feature"])?;
+    commit(
+        root,
+        &[
+            ("lib.rs", Some("pub fn a() { feature() }\n")),
+            ("new.rs", Some("pub fn feature() {}\n")),
+            ("old.rs", None),
+            ("notes.txt", Some("v2\n")),
+        ],
+        "Feature",
+    )?;
+    git(root, &["checkout", "-q", "main"])?;
+    commit(root, &[("main_only.rs", Some("pub fn later() {}\n"))], "Later on main")?;
+    git(root, &["checkout", "-q", "feature"])?;
+    Ok(dir)
+}
+
+#[test]
+fn base_diff_covers_the_branch_and_respects_filters() -> anyhow::Result<()> {
+    let dir = repository()?;
+    let options = FlattenOptions::default().target_dirs([dir.path()]).extensions([".rs"]);
+    let result = Flattener::new(options.clone().git_diff_base("main")).run()?;
+
+    let git = result.git_changes.expect("git section");
+    assert_eq!(git.diff_base.as_deref(), Some("main"));
+    assert!(git.status.is_none() && git.unstaged_diff.is_none());
+    let diff = git.base_diff.expect("base diff");
+    let files: Vec<&str> = diff.lines().filter_map(|line| line.strip_prefix("diff --git a/")).collect();
+    assert_eq!(files, ["lib.rs b/lib.rs", "new.rs b/new.rs", "old.rs b/old.rs"]);
+    assert!(diff.contains("+pub fn a() { feature() }"));
+    assert!(result.content.contains("## Git Diff (main...HEAD):\n```diff\ndiff --git a/lib.rs"));
+
+    let error = Flattener::new(options.git_diff_base("no-such-branch")).run().unwrap_err();
+    assert!(error.to_string().starts_with("Cannot diff against 'no-such-branch'"));
+    Ok(())
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use code_flattener::{FlattenOptions, Flattener, OutputFormat};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::git;
+
+fn repository() -> anyhow::Result<tempfile::TempDir> {
+    let dir = tempdir()?;
+    git(dir.path(), &["init", "-q"])?;
+    for (i, subject) in ["Add parser", "Handle empty input", "Speed up tokenizer"].iter().enumerate() {
+        fs::write(dir.path().join("lib.rs"), format!("pub const VERSION: u32 = {};\n", i))?;
+        git(dir.path(), &["add", "lib.rs"])?;
+        git(dir.path(), &["commit", "-q", "-m", subject])?;
+    }
+    Ok(dir)
+}
+
+fn options(dir: &Path) -> FlattenOptions {
+    FlattenOptions::default().target_dirs([dir]).extensions([".rs"]).git_log(2)
+}
+
+#[test]
+fn git_log_lists_the_most_recent_commits() -> anyhow::Result<()> {
+    let dir = repository()?;
+    let result = Flattener::new(options(dir.path())).run()?;
+
+    let git = result.git_changes.expect("git section");
+    assert!(git.status.is_none() && git.staged_diff.is_none());
+    let log = git.log.expect("git log");
+    let subjects: Vec<&str> = log.lines().map(|line| line.split_once(": ").unwrap().1).collect();
+    assert_eq!(subjects, ["Speed up tokenizer", "Handle empty input"]);
+    assert!(log.lines().all(|line| line.contains(" Jane Doe: ")));
+    assert!(result.content.contains("## Git Log:\n```text\n"));
+
+    let stat = Flattener::new(options(dir.path()).git_log_stat(true).format(OutputFormat::Json)).run()?;
+    let doc: serde_json::Value = serde_json::from_str(&stat.content)?;
+    let log = doc["git_changes"]["log"].as_str().unwrap();
+    assert_eq!(log.matches(" lib.rs | 2 +-").count(), 2);
+    Ok(())
+}
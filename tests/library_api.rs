@@ -0,0 +1,107 @@
+use code_flattener::{FlattenOptions, Flattener, OutputSink};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// These tests drive the library API directly, without going through the CLI.
+
+#[test]
+fn flattener_returns_files_with_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join("src").join("lib.rs"), "pub fn hello() {}\n")?;
+    fs::write(root.join("notes.txt"), "not included")?;
+
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs"])
+        .markdown(true);
+    let result = Flattener::new(options).run()?;
+
+    assert_eq!(result.file_count, 1);
+    assert_eq!(result.files.len(), 1);
+
+    let file = &result.files[0];
+    assert_eq!(file.metadata.relative_path, Path::new("src").join("lib.rs"));
+    assert_eq!(file.metadata.extension, "rs");
+    assert_eq!(file.metadata.size, 18);
+    assert_eq!(file.metadata.token_count, 4);
+    assert_eq!(file.content, "pub fn hello() {}\n");
+
+    assert!(result.content.contains("```rs\n# --- File: "));
+    assert!(!result.content.contains("not included"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn flattener_applies_profiles() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+
+    fs::write(root.join("main.rs"), "fn main() {}")?;
+    fs::write(root.join("Cargo.toml"), "[package]\nname = \"x\"\n")?;
+    fs::write(root.join("index.ts"), "export {}")?;
+
+    let options = FlattenOptions::new().target_dir(root).profile("rust");
+    let result = Flattener::new(options).run()?;
+
+    let mut names: Vec<_> = result
+        .files
+        .iter()
+        .map(|f| f.metadata.relative_path.to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Cargo.toml", "main.rs"]);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct RecordingSink {
+    chunks: Vec<String>,
+    finished: bool,
+}
+
+impl OutputSink for RecordingSink {
+    fn write_chunk(&mut self, chunk: &str) -> anyhow::Result<()> {
+        self.chunks.push(chunk.to_string());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.finished = true;
+        Ok(())
+    }
+}
+
+#[test]
+fn run_with_sink_streams_one_chunk_per_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+
+    for i in 0..20 {
+        fs::write(root.join(format!("file{:02}.rs", i)), format!("// file {}\n", i))?;
+    }
+
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs"])
+        .parallel(true);
+    let mut sink = RecordingSink::default();
+    let summary = Flattener::new(options).run_with_sink(&mut sink)?;
+
+    assert!(sink.finished);
+    assert_eq!(summary.file_count, 20);
+    assert_eq!(sink.chunks.len(), 20);
+    for (chunk, file) in sink.chunks.iter().zip(&summary.files) {
+        assert!(chunk.contains(&*file.path.to_string_lossy()));
+    }
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use assert_cmd::prelude::*;
+use code_flattener::{FlattenOptions, Flattener, Normalization, OutputFormat, ProfileManager};
+use serde_json::Value;
+use std::fs;
+use std::process::Command;
+use tempfile::tempdir;
+
+const MESSY: &str = "\u{FEFF}fn main() {  \r\n\r\n\r\n    let x = 1;\t\r\n\n\n}\r";
+
+fn content(options: FlattenOptions) -> anyhow::Result<String> {
+    Ok(Flattener::new(options).run()?.files.remove(0).content)
+}
+
+#[test]
+fn each_step_can_be_toggled() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join("main.rs"), MESSY)?;
+    let options = || FlattenOptions::new().target_dir(dir.path()).extensions([".rs"]);
+
+    // Only the BOM goes by default
+    assert_eq!(content(options())?, &MESSY[3..]);
+    assert_eq!(
+        content(options().normalize(Normalization { strip_bom: false, ..Normalization::default() }))?,
+        MESSY
+    );
+    assert_eq!(
+        content(options().normalize(Normalization { line_endings: true, ..Normalization::default() }))?,
+        "fn main() {  \n\n\n    let x = 1;\t\n\n\n}\n"
+    );
+    assert_eq!(
+        content(options().normalize(Normalization { trailing_whitespace: true, ..Normalization::default() }))?,
+        "fn main() {\r\n\r\n\r\n    let x = 1;\r\n\n\n}\r"
+    );
+    assert_eq!(content(options().normalize(Normalization::all()))?, "fn main() {\n\n    let x = 1;\n\n}\n");
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn config_and_profiles_enable_steps() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("main.rs"), MESSY)?;
+    fs::write(
+        root.join(".flattener.toml"),
+        "extensions = [\".rs\"]\n\n[normalize]\nline_endings = true\n",
+    )?;
+
+    let run = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let out = root.join("flat.json");
+        Command::cargo_bin("code-flattener")?
+            .current_dir(root)
+            .args(args)
+            .args(["--format", "json", "-o"])
+            .arg(&out)
+            .arg(".")
+            .assert()
+            .success();
+        let doc: Value = serde_json::from_str(&fs::read_to_string(&out)?)?;
+        Ok(doc["files"][0]["content"].as_str().unwrap_or_default().to_string())
+    };
+
+    assert_eq!(run(&[])?, "fn main() {  \n\n\n    let x = 1;\t\n\n\n}\n");
+    assert_eq!(run(&["--trim-trailing-whitespace"])?, "fn main() {\n\n\n    let x = 1;\n\n\n}\n");
+    assert_eq!(run(&["--normalize"])?, "fn main() {\n\n    let x = 1;\n\n}\n");
+
+    fs::write(
+        root.join(".flattener.toml"),
+        "[profiles.tidy]\nextensions = [\".rs\"]\n\n[profiles.tidy.normalize]\nblank_lines = true\nline_endings = true\n",
+    )?;
+    assert_eq!(run(&["--profile", "tidy"])?, "fn main() {  \n\n    let x = 1;\t\n\n}\n");
+
+    let options = FlattenOptions::new().target_dir(root).extensions([".rs"]).format(OutputFormat::Json);
+    assert!(Flattener::new(options).run()?.content.contains("\\r\\n"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn explicit_settings_override_config_and_profiles() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("main.rs"), MESSY)?;
+    fs::write(
+        root.join(".flattener.toml"),
+        "extensions = [\".rs\"]\n\n[normalize]\nstrip_bom = false\nline_endings = true\n",
+    )?;
+
+    let run = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let out = root.join("flat.json");
+        Command::cargo_bin("code-flattener")?
+            .current_dir(root)
+            .args(args)
+            .args(["--format", "json", "-o"])
+            .arg(&out)
+            .arg(".")
+            .assert()
+            .success();
+        let doc: Value = serde_json::from_str(&fs::read_to_string(&out)?)?;
+        Ok(doc["files"][0]["content"].as_str().unwrap_or_default().to_string())
+    };
+
+    assert_eq!(run(&["--no-normalize-line-endings"])?, MESSY);
+    assert_eq!(run(&["--strip-bom"])?, "fn main() {  \n\n\n    let x = 1;\t\n\n\n}\n");
+    assert_eq!(
+        run(&["--normalize", "--no-collapse-blank-lines"])?,
+        "\u{FEFF}fn main() {\n\n\n    let x = 1;\n\n\n}\n"
+    );
+    assert_eq!(run(&["--keep-bom", "--strip-bom", "--no-normalize-line-endings"])?, &MESSY[3..]);
+
+    // Options set in code win over a profile's table, even when they are the defaults
+    let mut profile = ProfileManager::new(None).resolve("rust").ok_or("no rust profile")?;
+    profile.normalize = Some(Normalization::all());
+    let mut options = FlattenOptions::new().normalize(Normalization::default());
+    options.apply_profile(profile.clone());
+    assert_eq!(options.normalize, Some(Normalization::default()));
+    let mut options = FlattenOptions::new();
+    options.apply_profile(profile);
+    assert_eq!(options.normalize, Some(Normalization::all()));
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use code_flattener::{FlattenOptions, Flattener};
+use tempfile::tempdir;
+
+use common::write;
+
+fn outline(name: &str, source: &str) -> anyhow::Result<String> {
+    let dir = tempdir()?;
+    write(dir.path(), name, source)?;
+    let options = FlattenOptions::new().target_dir(dir.path()).include_globs([name]).outline(true);
+    let file = Flattener::new(options).run()?.files.remove(0);
+    assert!(file.metadata.outline);
+    Ok(file.content)
+}
+
+#[test]
+fn function_bodies_are_elided() -> anyhow::Result<()> {
+    let rust = r#"/// A point.
+pub struct Point { pub x: i32 }
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str { "}" }
+}
+
+impl Point {
+    pub fn new<T: Into<i32>>(x: T) -> Self
+    where
+        T: Copy,
+    {
+        let brace = '{';
+        Self { x: x.into() }
+    }
+}
+"#;
+    assert_eq!(
+        outline("lib.rs", rust)?,
+        r#"/// A point.
+pub struct Point { pub x: i32 }
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str { ... }
+}
+
+impl Point {
+    pub fn new<T: Into<i32>>(x: T) -> Self
+    where
+        T: Copy,
+    { ... }
+}
+"#
+    );
+
+    let ts = "export interface Opts { f(): void }\nexport function f(a: string): string {\n  return `${a} }`;\n}\nexport class A extends B {\n  private n = 0;\n  async run(x: number): Promise<void> {\n    await go(x);\n  }\n}\nexport const h = (req) => {\n  return req;\n};\nconst config = { port: 80 };\n";
+    assert_eq!(
+        outline("app.ts", ts)?,
+        "export interface Opts { f(): void }\nexport function f(a: string): string { ... }\nexport class A extends B {\n  private n = 0;\n  async run(x: number): Promise<void> { ... }\n}\nexport const h = (req) => { ... };\nconst config = { port: 80 };\n"
+    );
+
+    let php = "<?php\nclass A {\n    public function f(int $x): array {\n        return ['}' => $x];\n    }\n}\n";
+    assert_eq!(outline("a.php", php)?, "<?php\nclass A {\n    public function f(int $x): array { ... }\n}\n");
+    Ok(())
+}
+
+#[test]
+fn python_bodies_keep_their_docstring() -> anyhow::Result<()> {
+    let python = r#"import os
+
+
+def top(a,
+        b=2):
+    """Top.
+
+Details."""
+    if a:
+        return b
+    return a
+
+
+class Service:
+    attr = 0
+
+    def __init__(self):
+        self.x = 1
+
+    @property
+    def value(self):
+        def inner():
+            pass
+        return self.x
+
+    def one(self): return 1
+"#;
+    assert_eq!(
+        outline("m.py", python)?,
+        r#"import os
+
+
+def top(a,
+        b=2):
+    """Top.
+
+Details."""
+    ...
+
+
+class Service:
+    attr = 0
+
+    def __init__(self):
+        ...
+
+    @property
+    def value(self):
+        ...
+
+    def one(self): return 1
+"#
+    );
+    Ok(())
+}
+
+#[test]
+fn outline_is_selected_per_glob() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let source = "fn f() {\n    body();\n}\n";
+    write(dir.path(), "src/core/a.rs", source)?;
+    write(dir.path(), "src/b.rs", source)?;
+    write(dir.path(), "notes.txt", "fn f() {\n}\n")?;
+
+    let run = |options: FlattenOptions| -> anyhow::Result<Vec<(String, bool)>> {
+        let mut files: Vec<_> = Flattener::new(options.target_dir(dir.path()).extensions([".rs", ".txt"]))
+            .run()?
+            .files
+            .into_iter()
+            .map(|f| (f.metadata.relative_path.to_string_lossy().replace('\\', "/"), f.metadata.outline))
+            .collect();
+        files.sort();
+        Ok(files)
+    };
+    let outlined = |files: &[(String, bool)]| -> Vec<String> {
+        files.iter().filter(|(_, outline)| *outline).map(|(path, _)| path.clone()).collect()
+    };
+
+    let all_but_core = run(FlattenOptions::new().outline(true).full_globs(["src/core/**"]))?;
+    assert_eq!(outlined(&all_but_core), ["src/b.rs"]);
+    let only_core = run(FlattenOptions::new().outline_globs(["src/core/*"]))?;
+    assert_eq!(outlined(&only_core), ["src/core/a.rs"]);
+
+    let dir_path = dir.path().to_path_buf();
+    let output = Flattener::new(FlattenOptions::new().target_dir(&dir_path).extensions([".rs"]).outline(true)).run()?;
+    assert!(output.files.iter().all(|f| f.content == "fn f() { ... }\n"));
+    Ok(())
+}
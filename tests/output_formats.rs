@@ -0,0 +1,144 @@
+use code_flattener::{FlattenOptions, Flattener, OutputFormat, SortOrder};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// Structured output formats must be machine-readable without re-parsing file markers.
+
+fn sample_project(root: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join("src").join("main.rs"), "fn main() {\n    println!(\"\\\"hi\\\"\");\n}\n")?;
+    fs::write(root.join("README.md"), "# Title\n")?;
+    Ok(())
+}
+
+fn flatten(root: &Path, format: OutputFormat) -> anyhow::Result<String> {
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs", ".md"])
+        .sort(SortOrder::Path)
+        .format(format);
+    Ok(Flattener::new(options).run()?.content)
+}
+
+#[test]
+fn json_format_is_a_single_document() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    sample_project(dir.path())?;
+
+    let output = flatten(dir.path(), OutputFormat::Json)?;
+    let doc: Value = serde_json::from_str(&output)?;
+
+    assert_eq!(doc["header"]["generator"], "code-flattener");
+    let files = doc["files"].as_array().expect("files array");
+    assert_eq!(files.len(), 2);
+
+    let main = &files[1];
+    assert_eq!(main["path"], "src/main.rs");
+    assert_eq!(main["language"], "rust");
+    assert_eq!(main["content"], "fn main() {\n    println!(\"\\\"hi\\\"\");\n}\n");
+    assert_eq!(main["size"].as_u64(), Some(main["content"].as_str().unwrap().len() as u64));
+    assert_eq!(main["sha256"].as_str().map(str::len), Some(64));
+    assert!(main["tokens"].as_u64().unwrap() > 0);
+
+    assert_eq!(doc["summary"]["file_count"], 2);
+    assert!(doc["git_changes"].is_null());
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn jsonl_format_has_one_file_per_line() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    sample_project(dir.path())?;
+
+    let output = flatten(dir.path(), OutputFormat::Jsonl)?;
+    let lines: Vec<Value> = output
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], "file");
+    assert_eq!(lines[0]["path"], "README.md");
+    assert_eq!(lines[0]["language"], "markdown");
+    assert_eq!(lines[1]["path"], "src/main.rs");
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn xml_format_wraps_files_in_document_tags() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("a&b.rs"), "let x = a[b[0]]>c; // <tag>\n")?;
+
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs"])
+        .profile("rust")
+        .format(OutputFormat::Xml);
+    let output = Flattener::new(options).run()?.content;
+
+    assert!(output.starts_with("<repository>\n<root>"));
+    assert!(output.contains("<profile>rust</profile>\n"));
+    assert!(output.contains("<documents>\n<document index=\"1\">\n<source>a&amp;b.rs</source>\n"));
+    assert!(output.contains(
+        "<document_content><![CDATA[let x = a[b[0]]]]><![CDATA[>c; // <tag>\n]]></document_content>"
+    ));
+    assert!(output.ends_with("</document>\n</documents>\n</repository>\n"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn template_file_controls_the_output() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path().join("project");
+    sample_project(&root)?;
+    let template = dir.path().join("prompt.tmpl");
+    fs::write(
+        &template,
+        "{{#preamble}}\nFiles:\n{{/preamble}}\n\
+         {{#file}}\n[{{index}}] {{path}} ({{lang}}, {{tokens}} tokens)\n{{/file}}\n\
+         {{#epilogue}}\n{{file_count}} files\n{{/epilogue}}\n",
+    )?;
+
+    let options = FlattenOptions::new()
+        .target_dir(&root)
+        .extensions([".rs", ".md"])
+        .sort(SortOrder::Path)
+        .template(&template);
+    let result = Flattener::new(options).run()?;
+    let tokens: Vec<usize> = result.files.iter().map(|f| f.metadata.token_count).collect();
+
+    assert_eq!(
+        result.content,
+        format!(
+            "Files:\n[1] README.md (markdown, {} tokens)\n[2] src/main.rs (rust, {} tokens)\n2 files\n",
+            tokens[0], tokens[1]
+        )
+    );
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn template_with_unknown_placeholder_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    sample_project(dir.path())?;
+    let template = dir.path().join("bad.tmpl");
+    fs::write(&template, "{{path}}: {{contents}}\n")?;
+
+    let options = FlattenOptions::new().target_dir(dir.path()).template(&template);
+    let err = Flattener::new(options).run().unwrap_err();
+    assert!(format!("{:#}", err).contains("Unknown placeholder '{{contents}}'"));
+
+    dir.close()?;
+    Ok(())
+}
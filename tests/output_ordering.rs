@@ -0,0 +1,111 @@
+mod common;
+
+use assert_cmd::prelude::*;
+use code_flattener::{FlattenOptions, Flattener, SortOrder};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+use common::git;
+
+// Output order must not depend on thread scheduling: parallel and sequential runs over
+// the same tree have to produce byte-identical output.
+
+fn create_tree(root: &Path) -> std::io::Result<()> {
+    for dir in ["a", "b/c", "b/d", "e"] {
+        fs::create_dir_all(root.join(dir))?;
+        for i in 0..40 {
+            let body = "x".repeat((i * 37) % 500);
+            fs::write(root.join(dir).join(format!("f{}.rs", i)), format!("// {}/{}\n{}\n", dir, i, body))?;
+        }
+    }
+    Ok(())
+}
+
+// Log lines carry timestamps, so compare the written output file rather than stdout.
+fn run_cli(root: &Path, extra: &[&str]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let out_dir = tempdir()?;
+    let out_file = out_dir.path().join("flat.txt");
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(root)
+        .args(["--extensions", "rs", "-o"])
+        .arg(&out_file)
+        .args(extra)
+        .arg(".");
+    cmd.assert().success();
+    Ok(fs::read(&out_file)?)
+}
+
+#[test]
+fn parallel_output_is_identical_to_sequential() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    create_tree(dir.path())?;
+
+    let sequential = run_cli(dir.path(), &[])?;
+    assert!(!sequential.is_empty());
+    for _ in 0..3 {
+        assert_eq!(run_cli(dir.path(), &["--parallel"])?, sequential);
+    }
+
+    let by_size = run_cli(dir.path(), &["--sort", "size"])?;
+    assert_eq!(run_cli(dir.path(), &["--sort", "size", "--parallel"])?, by_size);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sort_orders_files_by_size_and_path() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("b.rs"), "x")?;
+    fs::write(root.join("a.rs"), "xxx")?;
+    fs::write(root.join("c.rs"), "xx")?;
+
+    let names = |order: SortOrder| -> anyhow::Result<Vec<String>> {
+        let options = FlattenOptions::new()
+            .target_dir(root)
+            .extensions([".rs"])
+            .parallel(true)
+            .sort(order);
+        Ok(Flattener::new(options)
+            .run()?
+            .files
+            .iter()
+            .map(|f| f.metadata.relative_path.to_string_lossy().into_owned())
+            .collect())
+    };
+
+    assert_eq!(names(SortOrder::Size)?, ["b.rs", "c.rs", "a.rs"]);
+    assert_eq!(names(SortOrder::Path)?, ["a.rs", "b.rs", "c.rs"]);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn churn_counts_paths_git_would_quote() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    git(root, &["init", "-q"])?;
+    let files = ["\u{fc}ber.rs", "say \"hi\".rs", "a.rs"];
+    for (i, name) in files.iter().enumerate() {
+        // The first file is touched by three commits, the last by one
+        for commit in i..files.len() {
+            fs::write(root.join(name), format!("// {}\n", commit))?;
+            git(root, &["add", "-A"])?;
+            git(root, &["commit", "-q", "-m", &format!("{} {}", name, commit)])?;
+        }
+    }
+
+    let options = FlattenOptions::new().target_dir(root).extensions([".rs"]).sort(SortOrder::Churn);
+    let names: Vec<String> = Flattener::new(options)
+        .run()?
+        .files
+        .iter()
+        .map(|f| f.metadata.relative_path.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, files);
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+// include_patterns / exclude_patterns are regular expressions over the relative path,
+// or over the file content with `pattern_target = "content"`.
+
+fn create_project(root: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(root.join("src/api"))?;
+    fs::create_dir_all(root.join("tests"))?;
+    fs::write(root.join("src/lib.rs"), "pub mod api;\n")?;
+    fs::write(root.join("src/api/mod.rs"), "pub fn handler() {}\n")?;
+    fs::write(root.join("src/api/schema.rs"), "// @generated by codegen\npub struct Schema;\n")?;
+    fs::write(root.join("tests/api_test.rs"), "#[test]\nfn works() {}\n")?;
+    Ok(())
+}
+
+fn flatten(root: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let out = root.join("flat.txt");
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(root)
+        .args(["--extensions", "rs", "-o"])
+        .arg(&out)
+        .args(args)
+        .arg(".");
+    cmd.assert().success();
+    Ok(fs::read_to_string(out)?)
+}
+
+#[test]
+fn exclude_patterns_match_relative_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    create_project(dir.path())?;
+
+    let output = flatten(dir.path(), &["--exclude-patterns", r"_test\.rs$", "--exclude-patterns", "^src/api/s"])?;
+    assert!(output.contains("src/lib.rs ---"));
+    assert!(output.contains("src/api/mod.rs ---"));
+    assert!(!output.contains("api_test.rs"));
+    assert!(!output.contains("schema.rs"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn profile_include_patterns_are_applied() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    create_project(root)?;
+    let conf = r#"
+[profiles.api-only]
+profile = "rust"
+include_patterns = ["^src/api/"]
+exclude_patterns = ["schema"]
+"#;
+    fs::write(root.join(".flattener.toml"), conf)?;
+
+    let output = flatten(root, &["--profile", "api-only"])?;
+    assert!(output.contains("src/api/mod.rs ---"));
+    assert!(!output.contains("src/lib.rs ---"));
+    assert!(!output.contains("schema.rs"));
+    assert!(!output.contains("api_test.rs"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn content_mode_matches_file_contents() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    create_project(dir.path())?;
+
+    let output = flatten(dir.path(), &["--pattern-target", "content", "--exclude-patterns", "@generated"])?;
+    assert!(!output.contains("schema.rs"));
+    assert!(output.contains("src/api/mod.rs ---"));
+
+    let output = flatten(dir.path(), &["--pattern-target", "content", "--include-patterns", r"#\[test\]"])?;
+    assert!(output.contains("tests/api_test.rs ---"));
+    assert!(!output.contains("src/lib.rs ---"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn explicit_path_target_overrides_profile() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    create_project(root)?;
+    let conf = r#"
+[profiles.generated]
+profile = "rust"
+pattern_target = "content"
+exclude_patterns = ["generated|api_test"]
+"#;
+    fs::write(root.join(".flattener.toml"), conf)?;
+
+    let output = flatten(root, &["--profile", "generated"])?;
+    assert!(!output.contains("schema.rs"));
+    assert!(output.contains("tests/api_test.rs ---"));
+
+    let output = flatten(root, &["--profile", "generated", "--pattern-target", "path"])?;
+    assert!(output.contains("src/api/schema.rs ---"));
+    assert!(!output.contains("api_test.rs"));
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn invalid_pattern_is_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    create_project(dir.path())?;
+
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(dir.path())
+        .args(["--extensions", "rs", "--exclude-patterns", "(unclosed", "."]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid exclude pattern '(unclosed'"));
+
+    dir.close()?;
+    Ok(())
+}
@@ -1,7 +1,6 @@
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::process::Command;
 use tempfile::tempdir;
 
@@ -0,0 +1,104 @@
+use assert_cmd::Command;
+use code_flattener::{FlattenOptions, Flattener, RedactRule, RuleMatches};
+use std::fs;
+use tempfile::tempdir;
+
+const CONFIG: &str = r#"
+[[redact]]
+name = "internal-hosts"
+pattern = '[a-z0-9-]+\.corp\.example\.com'
+replacement = "[HOST]"
+
+[profiles.base]
+extensions = [".py", ".md"]
+
+[[profiles.base.redact]]
+name = "emails"
+pattern = '(?P<user>[a-z.]+)@example\.com'
+replacement = "${user}@[EMAIL]"
+
+[profiles.client]
+extends = "base"
+
+[[profiles.client.redact]]
+name = "customers"
+regex = "Acme Corp|Globex"
+globs = ["src/**"]
+"#;
+
+fn project() -> anyhow::Result<tempfile::TempDir> {
+    let dir = tempdir()?;
+    fs::create_dir_all(dir.path().join("src"))?;
+    fs::write(
+        dir.path().join("src/sync.py"),
+        "# Owner: jane.doe@example.com\nHOST = \"db-1.corp.example.com\"\nCLIENT = \"Acme Corp\"\n",
+    )?;
+    fs::write(dir.path().join("NOTES.md"), "Globex uses api.corp.example.com\n")?;
+    fs::write(dir.path().join(".flattener.toml"), CONFIG)?;
+    Ok(dir)
+}
+
+#[test]
+fn rules_replace_matches_within_their_globs() -> anyhow::Result<()> {
+    let dir = project()?;
+    let options = FlattenOptions::default()
+        .target_dirs([dir.path()])
+        .extensions([".py", ".md"])
+        .redact_secrets(false)
+        .redact_rules([
+            RedactRule::new(r"[a-z0-9-]+\.corp\.example\.com").name("hosts").replacement("[HOST]"),
+            RedactRule::new("Acme Corp|Globex").globs(["src/**"]),
+        ]);
+    let result = Flattener::new(options).run()?;
+
+    let file = |name: &str| result.files.iter().find(|f| f.metadata.relative_path.ends_with(name)).unwrap();
+    let sync = file("sync.py");
+    assert_eq!(sync.content, "# Owner: jane.doe@example.com\nHOST = \"[HOST]\"\nCLIENT = \"[REDACTED]\"\n");
+    let found: Vec<(&str, usize, bool)> =
+        sync.metadata.redactions.iter().map(|r| (r.kind.as_str(), r.line, r.secret)).collect();
+    assert_eq!(found, [("hosts", 2, false), ("Acme Corp|Globex", 3, false)]);
+    assert_eq!(file("NOTES.md").content, "Globex uses [HOST]\n");
+
+    assert_eq!(
+        result.rule_matches,
+        [
+            RuleMatches { rule: "hosts".to_string(), matches: 2, files: 2 },
+            RuleMatches { rule: "Acme Corp|Globex".to_string(), matches: 1, files: 1 },
+        ]
+    );
+
+    let invalid = FlattenOptions::default()
+        .target_dirs([dir.path()])
+        .extensions([".py"])
+        .redact_rules([RedactRule::new("(")]);
+    let error = Flattener::new(invalid).run().unwrap_err();
+    assert!(format!("{:#}", error).contains("Invalid pattern in redact rule '('"));
+    Ok(())
+}
+
+#[test]
+fn profile_rules_are_inherited_and_counted_in_dry_runs() -> anyhow::Result<()> {
+    let dir = project()?;
+    let output = dir.path().join("out.txt");
+    Command::cargo_bin("code-flattener")?
+        .current_dir(dir.path())
+        .args(["--profile", "client", "-o"])
+        .arg(&output)
+        .arg(".")
+        .assert()
+        .success();
+    let content = fs::read_to_string(&output)?;
+    assert!(content.contains("# Owner: jane.doe@[EMAIL]\nHOST = \"[HOST]\"\nCLIENT = \"[REDACTED]\"\n"));
+    assert!(content.contains("Globex uses [HOST]\n"));
+
+    let assert = Command::cargo_bin("code-flattener")?
+        .current_dir(dir.path())
+        .args(["--profile", "client", "--dry-run", "."])
+        .assert()
+        .success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains(
+        "Redact rule matches:\n  internal-hosts: 2 matches in 2 files\n  emails: 1 matches in 1 files\n  customers: 1 matches in 1 files\n"
+    ));
+    Ok(())
+}
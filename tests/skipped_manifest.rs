@@ -0,0 +1,80 @@
+use code_flattener::{EncodingPolicy, FlattenOptions, Flattener, OutputFormat};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn setup(root: &Path) -> std::io::Result<()> {
+    fs::write(root.join("main.rs"), "fn main() {}\n")?;
+    fs::write(root.join("big.rs"), "a".repeat(4096))?;
+    fs::write(root.join("blob.rs"), b"\x00\x01\x02\x03")?;
+    fs::write(root.join("latin1.rs"), b"// caf\xe9\n")?;
+    fs::write(root.join("notes.md"), "not selected\n")?;
+    Ok(())
+}
+
+fn options(root: &Path) -> FlattenOptions {
+    FlattenOptions::new()
+        .target_dir(root)
+        .extensions([".rs"])
+        .max_size(0.001)
+        .encoding_fallback(EncodingPolicy::Error)
+        .list_skipped(true)
+}
+
+#[test]
+fn skipped_files_are_listed_with_reason_and_size() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    setup(root)?;
+
+    // Without the manifest, the non-UTF-8 file fails a sequential run
+    assert!(Flattener::new(options(root).list_skipped(false)).run().is_err());
+
+    for parallel in [false, true] {
+        let result = Flattener::new(options(root).parallel(parallel)).run()?;
+        assert_eq!(result.file_count, 1);
+        let skipped: Vec<(String, &str)> = result
+            .skipped
+            .iter()
+            .map(|f| (f.relative_path.to_string_lossy().into_owned(), f.reason.kind()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("big.rs".to_string(), "too_large"),
+                ("blob.rs".to_string(), "binary_content"),
+                ("latin1.rs".to_string(), "not_utf8"),
+            ]
+        );
+        assert!(result.content.contains("# --- Skipped Files (not in this output) ---\n"));
+        assert!(result.content.contains(
+            "# big.rs (4096 bytes): 4096 bytes exceeds the size limit of 1048 bytes\n"
+        ));
+        assert!(result.content.contains("# latin1.rs (8 bytes): content is not valid UTF-8\n"));
+        assert!(!result.content.contains("notes.md"));
+    }
+
+    let json = Flattener::new(options(root).format(OutputFormat::Json)).run()?.content;
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    assert_eq!(value["skipped"][1]["path"], "blob.rs");
+    assert_eq!(value["skipped"][1]["reason"], "binary_content");
+    assert_eq!(value["skipped"][1]["size"], 4);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn manifest_is_opt_in() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("main.rs"), "fn main() {}\n")?;
+    fs::write(root.join("big.rs"), "a".repeat(4096))?;
+
+    let result = Flattener::new(options(root).list_skipped(false)).run()?;
+    assert_eq!(result.skipped.len(), 1);
+    assert!(!result.content.contains("Skipped Files"));
+
+    dir.close()?;
+    Ok(())
+}
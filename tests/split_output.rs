@@ -0,0 +1,112 @@
+use assert_cmd::prelude::*;
+use std::fs;
+use std::process::Command;
+use tempfile::tempdir;
+
+// Split output keeps files whole unless a single file is larger than a part.
+
+#[test]
+fn split_bytes_writes_numbered_parts_with_index() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path().join("proj");
+    fs::create_dir_all(&root)?;
+    for i in 0..6 {
+        fs::write(root.join(format!("f{}.rs", i)), format!("// file {}\n{}\n", i, "x".repeat(300)))?;
+    }
+    let big: String = (0..200).map(|i| format!("line {}\n", i)).collect();
+    fs::write(root.join("z_big.rs"), &big)?;
+
+    let out = dir.path().join("out").join("flat.txt");
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(&root)
+        .args(["--extensions", "rs", "--split-bytes", "1000", "-o"])
+        .arg(&out)
+        .arg(".");
+    cmd.assert().success();
+
+    let mut parts = Vec::new();
+    for n in 1.. {
+        let path = dir.path().join("out").join(format!("flat.part{}.txt", n));
+        if !path.exists() {
+            break;
+        }
+        parts.push(fs::read_to_string(path)?);
+    }
+    assert!(!out.exists());
+    assert!(parts.len() >= 4, "expected several parts, got {}", parts.len());
+
+    for (i, part) in parts.iter().enumerate() {
+        assert!(part.starts_with(&format!("# --- Part {} of {} ---\n# Files in this part:\n", i + 1, parts.len())));
+        assert!(part.len() <= 1000, "part {} has {} bytes", i + 1, part.len());
+    }
+
+    // Small files land in exactly one part, listed in that part's index
+    for i in 0..6 {
+        let marker = format!("// file {}\n", i);
+        let holders: Vec<_> = parts.iter().filter(|p| p.contains(&marker)).collect();
+        assert_eq!(holders.len(), 1);
+        assert!(holders[0].contains(&format!("#   f{}.rs\n", i)));
+    }
+
+    // The oversized file is cut at line boundaries and labelled piece by piece
+    assert!(parts.iter().any(|p| p.contains("#   z_big.rs (piece 1 of ")));
+    let rejoined: String = parts.iter().filter(|p| p.contains("#   z_big.rs (piece")).map(|p| p.as_str()).collect();
+    assert!(rejoined.contains("line 0\n") && rejoined.contains("line 199\n"));
+
+    // The index maps every file to the part holding it
+    let index = fs::read_to_string(dir.path().join("out").join("flat.index.txt"))?;
+    assert!(index.starts_with(&format!("# --- Index of {} parts ---\n# flat.part1.txt:\n", parts.len())));
+    for (i, part) in parts.iter().enumerate() {
+        let section = index.split(&format!("# flat.part{}.txt:\n", i + 1)).nth(1).ok_or("part missing")?;
+        let section = section.split("\n# ").next().unwrap_or_default();
+        for line in section.lines() {
+            assert!(part.contains(&format!("{}\n", line)), "{} is not in part {}", line, i + 1);
+        }
+    }
+    for i in 0..6 {
+        assert!(index.contains(&format!("#   f{}.rs\n", i)));
+    }
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn parts_stay_within_the_limit_and_stale_parts_are_removed() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path().join("proj");
+    fs::create_dir_all(&root)?;
+    for i in 0..150 {
+        fs::write(root.join(format!("f{:03}.rs", i)), format!("// file {}\n", i))?;
+    }
+    fs::write(root.join("long.rs"), format!("{}\n", "~".repeat(2000)))?;
+
+    let out = dir.path().join("flat.txt");
+    let split = |limit: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Command::cargo_bin("code-flattener")?
+            .current_dir(&root)
+            .args(["--extensions", "rs", "--split-bytes", limit, "-o"])
+            .arg(&out)
+            .arg(".")
+            .assert()
+            .success();
+        let mut parts = Vec::new();
+        while let Ok(part) = fs::read_to_string(dir.path().join(format!("flat.part{}.txt", parts.len() + 1))) {
+            parts.push(part);
+        }
+        Ok(parts)
+    };
+
+    // Over a hundred parts, and a line longer than a part cut within the line
+    let parts = split("200")?;
+    assert!(parts.len() > 100, "expected over 100 parts, got {}", parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        assert!(part.len() <= 200, "part {} has {} bytes", i + 1, part.len());
+    }
+    let long: usize = parts.iter().filter(|p| p.contains("#   long.rs (piece")).map(|p| p.matches('~').count()).sum();
+    assert_eq!(long, 2000);
+
+    // A run with fewer parts leaves none of the earlier ones behind
+    assert_eq!(split("100000")?.len(), 1);
+    Ok(())
+}
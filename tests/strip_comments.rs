@@ -0,0 +1,84 @@
+use code_flattener::{FlattenOptions, Flattener};
+use std::fs;
+use tempfile::tempdir;
+
+// Comments are removed with a small lexer per language, so comment markers inside
+// string literals (and regexes, raw strings, heredocs) survive.
+
+fn strip(name: &str, source: &str, docstrings: bool) -> anyhow::Result<String> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join(name), source)?;
+    let options = FlattenOptions::new()
+        .target_dir(dir.path())
+        .include_globs([name])
+        .strip_comments(true)
+        .strip_docstrings(docstrings);
+    Ok(Flattener::new(options).run()?.files.remove(0).content)
+}
+
+#[test]
+fn strips_comments_but_not_strings() -> anyhow::Result<()> {
+    let rust = r###"//! Crate docs
+use std::fmt; // trailing
+
+/// Documented
+/* block /* nested */ still comment */
+fn main() {
+    let url = "http://example.com // not a comment";
+    let raw = r#"/* not "a" comment */"#;
+    let c = '"'; let b = b'"'; // quote chars
+    let lt: &'static str = "x"; /* inline */ let y = 1;
+}
+"###;
+    assert_eq!(
+        strip("lib.rs", rust, false)?,
+        r###"//! Crate docs
+use std::fmt;
+
+/// Documented
+fn main() {
+    let url = "http://example.com // not a comment";
+    let raw = r#"/* not "a" comment */"#;
+    let c = '"'; let b = b'"';
+    let lt: &'static str = "x"; let y = 1;
+}
+"###
+    );
+
+    let js = "// header\nconst re = /\\/\\/ x/g; // strip\nconst s = `a // b ${x}`;\nconst d = a / b; // divide\n";
+    assert_eq!(strip("app.ts", js, false)?, "const re = /\\/\\/ x/g;\nconst s = `a // b ${x}`;\nconst d = a / b;\n");
+
+    let shell = "#!/bin/bash\n# comment\necho \"# not\" '# not' $# ${#arr[@]} # strip\ncat <<EOF\n# kept\nEOF\n";
+    assert_eq!(strip("run.sh", shell, false)?, "#!/bin/bash\necho \"# not\" '# not' $# ${#arr[@]}\ncat <<EOF\n# kept\nEOF\n");
+
+    let php = "<?php\n# hash\n#[Attribute]\nclass A { // x\n    public $s = \"// keep\"; /* gone */\n}\n";
+    assert_eq!(strip("a.php", php, false)?, "<?php\n#[Attribute]\nclass A {\n    public $s = \"// keep\";\n}\n");
+
+    let css = "/* header */\na { background: url(\"//cdn/x.png\"); } /* tail */\n";
+    assert_eq!(strip("site.css", css, false)?, "a { background: url(\"//cdn/x.png\"); }\n");
+
+    let cpp = "auto s = R\"x(// keep)x\"; // strip\nint n = 1'000'000; // digits\nchar q = '\\''; // quote\n";
+    assert_eq!(strip("main.cpp", cpp, false)?, "auto s = R\"x(// keep)x\";\nint n = 1'000'000;\nchar q = '\\'';\n");
+    Ok(())
+}
+
+#[test]
+fn docstrings_are_kept_unless_requested() -> anyhow::Result<()> {
+    let python = "#!/usr/bin/env python3\n\"\"\"Module.\"\"\"\nimport os  # trailing\n\ndef f(x):\n    \"\"\"Doc.\"\"\"\n    s = \"# not\"\n    t = '''# keep'''\n    return x\n";
+    assert_eq!(
+        strip("m.py", python, false)?,
+        "#!/usr/bin/env python3\n\"\"\"Module.\"\"\"\nimport os\n\ndef f(x):\n    \"\"\"Doc.\"\"\"\n    s = \"# not\"\n    t = '''# keep'''\n    return x\n"
+    );
+    assert_eq!(
+        strip("m.py", python, true)?,
+        "#!/usr/bin/env python3\nimport os\n\ndef f(x):\n    s = \"# not\"\n    t = '''# keep'''\n    return x\n"
+    );
+
+    let js = "/** JSDoc */\nfunction f() {} // x\n";
+    assert_eq!(strip("a.js", js, false)?, "/** JSDoc */\nfunction f() {}\n");
+    assert_eq!(strip("a.js", js, true)?, "function f() {}\n");
+
+    // Languages without a lexer are left alone
+    assert_eq!(strip("notes.txt", "# heading // text\n", true)?, "# heading // text\n");
+    Ok(())
+}
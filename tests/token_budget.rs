@@ -0,0 +1,141 @@
+mod common;
+
+use assert_cmd::Command;
+use code_flattener::{count_tokens, FlattenOptions, Flattener, OutputFormat, Tokenizer};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::{git, write};
+
+// With --max-tokens the output keeps the highest-priority files that fit and lists the rest.
+// Most tests count whitespace-separated words to keep the arithmetic readable.
+
+fn words(n: usize) -> String {
+    vec!["word"; n].join(" ")
+}
+
+#[test]
+fn max_tokens_keeps_priority_files_and_lists_omitted() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::create_dir_all(root.join("src/core"))?;
+    fs::create_dir_all(root.join("src/util"))?;
+    fs::write(root.join("src/core/engine.rs"), words(40))?;
+    fs::write(root.join("src/util/a.rs"), words(5))?;
+    fs::write(root.join("src/util/b.rs"), words(10))?;
+    fs::write(root.join("src/util/huge.rs"), words(200))?;
+
+    // Whitespace token counting: each plain file chunk costs its words plus 5 marker tokens
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .include_globs(["src/core/**", "**/*.rs"])
+        .tokenizer(Tokenizer::Whitespace)
+        .max_tokens(90);
+    let result = Flattener::new(options).run()?;
+
+    let kept: Vec<_> = result
+        .files
+        .iter()
+        .map(|f| f.metadata.relative_path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    assert_eq!(kept, ["src/core/engine.rs", "src/util/a.rs", "src/util/b.rs"]);
+
+    assert_eq!(result.omitted.len(), 1);
+    assert_eq!(result.omitted[0].relative_path, Path::new("src").join("util").join("huge.rs"));
+    assert_eq!(result.omitted[0].token_count, 205);
+    assert!(result.token_count <= 90);
+    assert!(result.content.ends_with(
+        "# --- Omitted Files (token budget exceeded) ---\n# src/util/huge.rs (205 tokens)\n"
+    ));
+
+    // The engine file wins over smaller files because it matches the first include glob
+    let options = FlattenOptions::new()
+        .target_dir(root)
+        .include_globs(["src/core/**", "**/*.rs"])
+        .tokenizer(Tokenizer::Whitespace)
+        .max_tokens(65);
+    let result = Flattener::new(options).run()?;
+    assert_eq!(result.file_count, 1);
+    assert_eq!(result.omitted.len(), 3);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn max_tokens_counts_the_git_section_and_epilogue() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    git(root, &["init", "-q"])?;
+    for (i, name) in ["a.rs", "b.rs", "c.rs", "d.rs"].iter().enumerate() {
+        write(root, name, words(20 + 10 * i))?;
+    }
+    git(root, &["add", "-A"])?;
+    git(root, &["commit", "-q", "-m", "Initial"])?;
+    write(root, "a.rs", words(12))?;
+
+    for format in [OutputFormat::Plain, OutputFormat::Json, OutputFormat::Xml] {
+        for max_tokens in [40, 150] {
+            let options = FlattenOptions::new()
+                .target_dir(root)
+                .extensions([".rs"])
+                .format(format)
+                .include_git_changes(true)
+                .git_log(3)
+                .tokenizer(Tokenizer::Whitespace)
+                .max_tokens(max_tokens);
+            let result = Flattener::new(options).run()?;
+            assert!(!result.omitted.is_empty());
+            // 40 tokens are too few for the git section, which is then left out
+            assert_eq!(result.git_changes.is_some(), max_tokens == 150);
+            assert!(result.token_count <= max_tokens, "{:?}: {} > {}", format, result.token_count, max_tokens);
+            assert!(count_tokens(&result.content, Tokenizer::Whitespace) <= max_tokens);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn recently_changed_paths_git_would_quote_come_first() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    git(root, &["init", "-q"])?;
+    for name in ["a.rs", "say \"hi\".rs", "\u{fc}ber.rs"] {
+        write(root, name, words(20))?;
+    }
+    git(root, &["add", "-A"])?;
+    git(root, &["commit", "-q", "-m", "Initial"])?;
+    write(root, "say \"hi\".rs", words(20) + " ")?;
+    git(root, &["commit", "-q", "-am", "Touch"])?;
+    write(root, "\u{fc}ber.rs", words(20) + " ")?;
+
+    let options = FlattenOptions::new().target_dir(root).extensions([".rs"]).tokenizer(Tokenizer::Whitespace).max_tokens(70);
+    let result = Flattener::new(options).run()?;
+    let omitted: Vec<&Path> = result.omitted.iter().map(|f| f.relative_path.as_path()).collect();
+    assert_eq!(omitted, [Path::new("a.rs")]);
+    Ok(())
+}
+
+#[test]
+fn max_tokens_counts_bpe_tokens_by_default() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    for name in ["a.rs", "b.rs", "c.rs", "d.rs"] {
+        write(root, name, "fn f(x: &[u8]) -> Option<(usize, u8)> { x.iter().copied().enumerate().max_by_key(|(_, b)| *b) }\n".repeat(4))?;
+    }
+    let output = root.join("out.txt");
+    Command::cargo_bin("code-flattener")?
+        .current_dir(root)
+        .args(["--extensions", ".rs", "--max-tokens", "200", "-o"])
+        .arg(&output)
+        .arg(".")
+        .assert()
+        .success();
+    let content = fs::read_to_string(&output)?;
+    let bpe = count_tokens(&content, Tokenizer::P50kBase);
+    assert!(bpe <= 200, "{} p50k tokens", bpe);
+    // A word count would have let far more through
+    assert!(content.contains("Omitted Files") && count_tokens(&content, Tokenizer::Whitespace) * 2 < bpe);
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use assert_cmd::prelude::*;
+use code_flattener::{FlattenOptions, Flattener, TokenStats};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+// The stats report attributes tokens to files, every enclosing directory and extensions.
+
+fn sample_project(root: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(root.join("src/gen"))?;
+    fs::write(root.join("src/main.rs"), "one two three\nfour\n")?;
+    fs::write(root.join("src/gen/big.rs"), "a b c d e f g h i j\n")?;
+    fs::write(root.join("README.md"), "# Title\n")?;
+    Ok(())
+}
+
+#[test]
+fn stats_aggregate_per_directory_and_extension() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    sample_project(dir.path())?;
+
+    let options = FlattenOptions::new().target_dir(dir.path()).extensions([".rs", ".md"]);
+    let summary = Flattener::new(options).run()?;
+    let files: Vec<_> = summary.files.iter().map(|f| f.metadata.clone()).collect();
+    let stats = TokenStats::from_files(&files);
+
+    let names = |rows: &[code_flattener::StatsRow]| rows.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&stats.files), ["src/gen/big.rs", "src/main.rs", "README.md"]);
+    assert_eq!(names(&stats.directories), ["src", "src/gen"]);
+    assert_eq!(names(&stats.extensions), [".rs", ".md"]);
+
+    let src = &stats.directories[0];
+    assert_eq!((src.files, src.tokens, src.lines), (2, 14, 3));
+    assert_eq!(stats.total.files, 3);
+    assert_eq!(stats.total.tokens, 16);
+    assert_eq!(stats.total.bytes, 20 + 19 + 8);
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn stats_json_is_printed_to_stderr() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    sample_project(dir.path())?;
+    let out = dir.path().join("flat.txt");
+
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(dir.path())
+        .args(["--extensions", "rs,md", "--stats=json", "-o"])
+        .arg(&out)
+        .arg(".");
+    let output = cmd.assert().success().get_output().stderr.clone();
+
+    let report: Value = serde_json::from_slice(&output)?;
+    assert_eq!(report["total"]["files"], 3);
+    assert_eq!(report["files"][0]["name"], "src/gen/big.rs");
+    assert_eq!(report["extensions"][0]["name"], ".rs");
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use assert_cmd::prelude::*;
+use code_flattener::{count_tokens, FlattenOptions, Flattener, OutputFormat, Tokenizer};
+use serde_json::Value;
+use std::fs;
+use std::process::Command;
+use tempfile::tempdir;
+
+// Every tokenizer backend counts the same text differently; the one in effect is named
+// in the output summary.
+
+const TEXT: &str = "fn main() {\n    println!(\"Hello, tokenizer!\");\n}\n";
+
+#[test]
+fn tokenizers_count_differently() {
+    assert_eq!(count_tokens(TEXT, Tokenizer::Whitespace), 6);
+    assert_eq!(count_tokens(TEXT, Tokenizer::Estimate), TEXT.len().div_ceil(4));
+    for tokenizer in [Tokenizer::Cl100kBase, Tokenizer::O200kBase, Tokenizer::P50kBase] {
+        let count = count_tokens(TEXT, tokenizer);
+        assert!(count > 6 && count < TEXT.len(), "{}: {}", tokenizer, count);
+    }
+    assert_eq!("o200k".parse::<Tokenizer>(), Ok(Tokenizer::O200kBase));
+    assert!("gpt5".parse::<Tokenizer>().is_err());
+}
+
+#[test]
+fn gpt4_tokens_means_cl100k_base() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join("main.rs"), TEXT)?;
+
+    let options = FlattenOptions::new().target_dir(dir.path()).extensions([".rs"]).gpt4_tokens(true);
+    let result = Flattener::new(options).run()?;
+    assert_eq!(result.tokenizer, Tokenizer::Cl100kBase);
+    assert_eq!(result.files[0].metadata.token_count, count_tokens(TEXT, Tokenizer::Cl100kBase));
+
+    let options = FlattenOptions::new()
+        .target_dir(dir.path())
+        .extensions([".rs"])
+        .tokenizer(Tokenizer::Estimate)
+        .format(OutputFormat::Json);
+    let doc: Value = serde_json::from_str(&Flattener::new(options).run()?.content)?;
+    assert_eq!(doc["summary"]["tokenizer"], "estimate");
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn custom_profile_selects_tokenizer() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("main.rs"), TEXT)?;
+    fs::write(
+        root.join(".flattener.toml"),
+        "[profiles.o200k-rust]\nprofile = \"rust\"\ntokenizer = \"o200k_base\"\n",
+    )?;
+
+    let out = root.join("flat.json");
+    let mut cmd = Command::cargo_bin("code-flattener")?;
+    cmd.current_dir(root)
+        .args(["--profile", "o200k-rust", "--format", "json", "-o"])
+        .arg(&out)
+        .arg(".");
+    cmd.assert().success();
+
+    let doc: Value = serde_json::from_str(&fs::read_to_string(&out)?)?;
+    assert_eq!(doc["summary"]["tokenizer"], "o200k_base");
+    let main = doc["files"]
+        .as_array()
+        .and_then(|files| files.iter().find(|f| f["path"] == "main.rs"))
+        .expect("main.rs in output");
+    assert_eq!(main["tokens"], count_tokens(TEXT, Tokenizer::O200kBase));
+
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use assert_cmd::Command;
+use code_flattener::{FlattenOptions, Flattener, Tokenizer, TruncateRule, Truncation};
+use std::fs;
+use tempfile::tempdir;
+
+fn numbered_lines(count: usize) -> String {
+    (1..=count).map(|i| format!("line {}\n", i)).collect()
+}
+
+#[test]
+fn oversized_files_keep_head_and_tail() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    // 2000 lines of 7-9 bytes: over a 0.01 MB limit
+    fs::write(dir.path().join("schema.sql"), numbered_lines(2000))?;
+    fs::write(dir.path().join("data.sql"), numbered_lines(2000))?;
+    fs::write(dir.path().join("small.sql"), numbered_lines(5))?;
+
+    let options = FlattenOptions::new()
+        .target_dir(dir.path())
+        .extensions([".sql"])
+        .max_size(0.01)
+        .truncate("3:2".parse().map_err(anyhow::Error::msg)?)
+        .truncate_globs(["schema*"])
+        .list_skipped(true);
+    let result = Flattener::new(options).run()?;
+
+    let content = |name: &str| {
+        result
+            .files
+            .iter()
+            .find(|f| f.metadata.relative_path.to_string_lossy() == name)
+            .map(|f| (f.content.clone(), f.metadata.truncated_lines))
+    };
+    assert_eq!(
+        content("schema.sql"),
+        Some(("line 1\nline 2\nline 3\n... [truncated 1995 lines] ...\nline 1999\nline 2000\n".to_string(), 1995))
+    );
+    assert_eq!(content("small.sql"), Some((numbered_lines(5), 0)));
+    // Oversized files outside `truncate_globs` are still skipped
+    assert_eq!(content("data.sql"), None);
+    assert_eq!(result.skipped.len(), 1);
+    assert_eq!(result.skipped[0].relative_path.to_string_lossy(), "data.sql");
+    Ok(())
+}
+
+#[test]
+fn token_truncation_keeps_whole_lines() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join("big.sql"), numbered_lines(2000))?;
+
+    // Two whitespace tokens per line: 5 tokens fit two lines, 4 fit two lines
+    let options = FlattenOptions::new()
+        .target_dir(dir.path())
+        .extensions([".sql"])
+        .max_size(0.01)
+        .tokenizer(Tokenizer::Whitespace)
+        .truncate(Truncation::Tokens { head: 5, tail: 4 });
+    let file = Flattener::new(options).run()?.files.remove(0);
+    assert_eq!(file.content, "line 1\nline 2\n... [truncated 1996 lines] ...\nline 1999\nline 2000\n");
+    Ok(())
+}
+
+#[test]
+fn truncate_rules_give_globs_their_own_spec() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    for name in ["schema.sql", "data.sql", "app.log"] {
+        fs::write(dir.path().join(name), numbered_lines(2000))?;
+    }
+    let options = FlattenOptions::new()
+        .target_dir(dir.path())
+        .extensions([".sql", ".log"])
+        .max_size(0.01)
+        .truncate(Truncation::Lines { head: 1, tail: 1 })
+        .truncate_rules([
+            TruncateRule::new("schema*", Truncation::Lines { head: 3, tail: 0 }),
+            TruncateRule::new("*.log", Truncation::Lines { head: 0, tail: 2 }),
+        ]);
+    let result = Flattener::new(options).run()?;
+    let content = |name: &str| {
+        result.files.iter().find(|f| f.metadata.relative_path.to_string_lossy() == name).map(|f| f.content.clone())
+    };
+    assert_eq!(content("schema.sql").unwrap(), "line 1\nline 2\nline 3\n... [truncated 1997 lines] ...\n");
+    assert_eq!(content("app.log").unwrap(), "... [truncated 1998 lines] ...\nline 1999\nline 2000\n");
+    assert_eq!(content("data.sql").unwrap(), "line 1\n... [truncated 1998 lines] ...\nline 2000\n");
+
+    // From the config file, without `truncate`: files no rule matches are skipped
+    fs::write(dir.path().join(".flattener.toml"), "truncate_rules = [\"schema*=3:0\", \"*.log=0:2 lines\"]\n")?;
+    let output = dir.path().join("out.txt");
+    Command::cargo_bin("code-flattener")?
+        .current_dir(dir.path())
+        .args(["--extensions", ".sql,.log", "--max-size", "0.01", "--list-skipped", "-o"])
+        .arg(&output)
+        .arg(".")
+        .assert()
+        .success();
+    let written = fs::read_to_string(&output)?;
+    assert!(written.contains("line 3\n... [truncated 1997 lines] ...\n"));
+    assert!(written.contains("... [truncated 1998 lines] ...\nline 1999\nline 2000\n"));
+    assert!(written.contains("schema.sql ---") && !written.contains("data.sql ---"));
+    Ok(())
+}
+
+#[test]
+fn truncation_specs_parse() {
+    assert_eq!("200:50".parse(), Ok(Truncation::Lines { head: 200, tail: 50 }));
+    assert_eq!("200:50 lines".parse(), Ok(Truncation::Lines { head: 200, tail: 50 }));
+    assert_eq!("2000:500tokens".parse(), Ok(Truncation::Tokens { head: 2000, tail: 500 }));
+    assert_eq!("100".parse(), Ok(Truncation::Lines { head: 100, tail: 0 }));
+    assert!("a:b".parse::<Truncation>().is_err());
+    assert_eq!(Truncation::Tokens { head: 1, tail: 2 }.to_string(), "1:2 tokens");
+    assert_eq!(
+        "**/*.sql=300:50".parse(),
+        Ok(TruncateRule::new("**/*.sql", Truncation::Lines { head: 300, tail: 50 }))
+    );
+    assert!("300:50".parse::<TruncateRule>().is_err());
+    assert!("**/*.sql=a:b".parse::<TruncateRule>().is_err());
+}